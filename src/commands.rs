@@ -0,0 +1,191 @@
+use std::rc::Rc;
+use chrono::{Local, NaiveDate};
+use structopt::StructOpt;
+use crate::task::Task;
+use crate::date_parser;
+use crate::merge::Resolution;
+use crate::{Params, add_task, apply_changes, complete_task, parse_sort, record_operation};
+
+/// The commands working on the task list without Rofi
+/// 
+/// Tasks are referred to by their line number in the file, as shown by `list`
+#[derive(StructOpt)]
+pub enum Command {
+    /// Add a task, written in the todo.txt format
+    Add {
+        /// The task, such as "(A) call bob @phone due:2026-11-01"
+        task : String
+    },
+    /// List the tasks to do, with their line number
+    List {
+        /// How to sort the tasks [default: the --sort option]
+        #[structopt(short = "s", long = "sort", possible_values = &["creation","content","priority","due"], case_insensitive = true)]
+        sort : Option<String>,
+        /// Only list the tasks of a project
+        #[structopt(short = "p", long = "project")]
+        project : Option<String>,
+        /// Only list the tasks of a context
+        #[structopt(short = "t", long = "context")]
+        context : Option<String>,
+        /// Also list the completed and upcoming tasks
+        #[structopt(short = "a", long = "all")]
+        all : bool
+    },
+    /// Mark a task as done
+    Done {
+        /// The line number of the task
+        line : usize
+    },
+    /// Remove a task
+    Rm {
+        /// The line number of the task
+        line : usize
+    },
+    /// Set the priority of a task, or remove it if no priority is given
+    Pri {
+        /// The line number of the task
+        line : usize,
+        /// The priority, from A to Z
+        priority : Option<char>
+    },
+    /// Set the due date of a task (YYYY-MM-DD), or remove it if no date is given
+    Due {
+        /// The line number of the task
+        line : usize,
+        /// The due date
+        date : Option<String>
+    }
+}
+
+/// Run a command and save the tasks
+/// 
+/// Arguments:
+/// 
+/// * `command` - the command to run
+/// * `params` - the tasks and their file
+pub fn run(command : Command, params : &mut Params) -> Result<(), String> {
+    match command {
+        Command::Add { task } => {
+            let mut task = Task::from_todotxt(date_parser::resolve_due_dates(&task, Local::now().date_naive()))?;
+            if task.creation_date.is_none() {
+                task.creation_date = Some(Local::now().date_naive());
+            }
+            let task = record_operation(params, |todos| add_task(todos, task));
+            save(params)?;
+            print_task(params, &task);
+        },
+        Command::List { sort, project, context, all } => {
+            let sort = match sort {
+                Some(sort) => parse_sort(&sort),
+                None => params.sort.clone()
+            };
+            let today = Local::now().date_naive();
+            let project = project.map(|p| String::from(p.trim_start_matches('+')));
+            let context = context.map(|c| String::from(c.trim_start_matches('@')));
+            let mut tasks = params.list.todos.get_main_index().iter()
+                .filter(|t| all || (!t.completion && !t.is_upcoming(today)))
+                .filter(|t| match &project { Some(p) => t.get_project_tags().contains(p), None => true })
+                .filter(|t| match &context { Some(c) => t.get_context_tags().contains(c), None => true })
+                .collect::<Vec<_>>();
+            tasks.sort_by(|a, b| a.comp(b, &sort));
+            for task in tasks {
+                print_task(params, task);
+            }
+        },
+        Command::Done { line } => {
+            let task = task_at(params, line)?;
+            if task.completion {
+                return Err(format!("Task {} is already done", line));
+            }
+            let task = record_operation(params, |todos| complete_task(todos, task));
+            save(params)?;
+            print_task(params, &task);
+        },
+        Command::Rm { line } => {
+            let task = task_at(params, line)?;
+            print_task(params, &task);
+            record_operation(params, |todos| { todos.remove(task); });
+            save(params)?;
+        },
+        Command::Pri { line, priority } => {
+            let priority = match priority {
+                Some(p) if p.is_ascii_alphabetic() => Some(p.to_ascii_uppercase()),
+                Some(p) => return Err(format!("malformed priority: {}", p)),
+                None => None
+            };
+            let task = task_at(params, line)?;
+            let task = update(params, task, |t| t.priority = priority);
+            save(params)?;
+            print_task(params, &task);
+        },
+        Command::Due { line, date } => {
+            let date = match date {
+                Some(date) => match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+                    Ok(date) => Some(date),
+                    Err(_) => return Err(format!("malformed date: {}", date))
+                },
+                None => None
+            };
+            let task = task_at(params, line)?;
+            let task = update(params, task, |t| t.set_due(date));
+            save(params)?;
+            print_task(params, &task);
+        }
+    }
+    Ok(())
+}
+
+/// Save the tasks
+/// 
+/// Without Rofi to ask the user, both versions of the tasks modified in the file meanwhile are kept
+/// 
+/// Arguments:
+/// 
+/// * `params` - the tasks and their file
+fn save(params : &mut Params) -> Result<(), String> {
+    let changes = params.list.file.save(params.list.todos.get_main_index(), |_| Resolution::Both)?;
+    apply_changes(&mut params.list.todos, changes);
+    Ok(())
+}
+
+/// Find the task at a line of the file
+/// 
+/// Arguments:
+/// 
+/// * `params` - the tasks and their file
+/// * `line` - the line number, starting at 1
+fn task_at(params : &Params, line : usize) -> Result<Rc<Task>, String> {
+    params.list.file.task_at(line)
+        .and_then(|id| params.list.todos.get_main_index().iter().find(|t| t.get_id() == id).cloned())
+        .ok_or(format!("No task at line {}", line))
+}
+
+/// Modify a task, recording the modification in the history
+/// 
+/// Returns the updated task
+/// 
+/// Arguments:
+/// 
+/// * `params` - the tasks and their history
+/// * `task` - the task to modify
+/// * `modify` - the modification to apply to the task
+fn update(params : &mut Params, task : Rc<Task>, modify : impl FnOnce(&mut Task)) -> Rc<Task> {
+    record_operation(params, |todos| {
+        let mut t = todos.remove(task).expect("Some references to task were not deleted");
+        modify(&mut t);
+        add_task(todos, t)
+    })
+}
+
+/// Print a task as a todo.txt line, preceded by its line number
+/// 
+/// Arguments:
+/// 
+/// * `params` - the tasks and their file
+/// * `task` - the task to print
+fn print_task(params : &Params, task : &Task) {
+    match params.list.file.line_number(task.get_id()) {
+        Some(line) => println!("{} {}", line, task.to_todotxt()),
+        None => println!("{}", task.to_todotxt())
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+
+/// Parse a date written by the user, relative to today
+/// 
+/// Accepted dates are `today`, `tomorrow`, a weekday (`fri`, `next monday`), an offset (`+3d`, `in 2 weeks`),
+/// the end of the month or of the year (`eom`, `eoy`), and absolute dates (`2026-12-24`, `24/12`, `24/12/2026`)
+/// 
+/// Arguments:
+/// 
+/// * `text` - the date written by the user
+/// * `today` - the current date
+pub fn parse_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let text = text.trim().to_lowercase();
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["eom"] => NaiveDate::from_ymd_opt(today.year(), today.month(), 1)?.checked_add_months(Months::new(1))?.pred_opt(),
+        ["eoy"] => NaiveDate::from_ymd_opt(today.year(), 12, 31),
+        ["next", day] => next_weekday(day.parse::<Weekday>().ok()?, today),
+        ["in", n, unit] => add(today, n.parse::<u32>().ok()?, unit),
+        [word] => match word.strip_prefix('+') {
+            Some(offset) => {
+                let split = offset.find(|c: char| !c.is_ascii_digit()).unwrap_or(offset.len());
+                let (n, unit) = offset.split_at(split);
+                add(today, n.parse::<u32>().ok()?, if unit.is_empty() { "d" } else { unit })
+            },
+            None => match word.parse::<Weekday>() {
+                Ok(day) => next_weekday(day, today),
+                Err(_) => absolute_date(word, today)
+            }
+        },
+        _ => None
+    }
+}
+
+/// Get the next date falling on a weekday, a week later if today is this weekday
+/// 
+/// Arguments:
+/// 
+/// * `day` - the weekday
+/// * `today` - the current date
+fn next_weekday(day: Weekday, today: NaiveDate) -> Option<NaiveDate> {
+    let days = (day.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today.checked_add_signed(Duration::days(if days == 0 { 7 } else { days as i64 }))
+}
+
+/// Add a number of days, weeks, months or years to a date
+/// 
+/// Arguments:
+/// 
+/// * `date` - the date
+/// * `n` - the number of units to add
+/// * `unit` - the unit, such as `d`, `day` or `days`
+fn add(date: NaiveDate, n: u32, unit: &str) -> Option<NaiveDate> {
+    match unit {
+        "d" | "day" | "days" => date.checked_add_signed(Duration::days(n as i64)),
+        "w" | "week" | "weeks" => date.checked_add_signed(Duration::weeks(n as i64)),
+        "m" | "month" | "months" => date.checked_add_months(Months::new(n)),
+        "y" | "year" | "years" => date.checked_add_months(Months::new(n.checked_mul(12)?)),
+        _ => None
+    }
+}
+
+/// Parse a date written as `2026-12-24`, `24/12/2026` or `24/12`, the next 24th of December
+/// 
+/// Arguments:
+/// 
+/// * `text` - the date
+/// * `today` - the current date
+fn absolute_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let parts = text.split('/').map(|p| p.parse::<u32>().ok()).collect::<Option<Vec<_>>>()?;
+    match parts.as_slice() {
+        [day, month, year] => NaiveDate::from_ymd_opt(*year as i32, *month, *day),
+        [day, month] => match NaiveDate::from_ymd_opt(today.year(), *month, *day) {
+            Some(date) if date >= today => Some(date),
+            _ => NaiveDate::from_ymd_opt(today.year() + 1, *month, *day)
+        },
+        _ => None
+    }
+}
+
+/// Replace the dates written by the user in the `due:` tags of a task by their `YYYY-MM-DD` form
+/// 
+/// The tags with a date which can't be parsed are kept as is
+/// 
+/// Arguments:
+/// 
+/// * `content` - the task, in todo.txt format
+/// * `today` - the current date
+pub fn resolve_due_dates(content: &str, today: NaiveDate) -> String {
+    content.split(' ')
+        .map(|word| match word.strip_prefix("due:").and_then(|date| parse_date(date, today)) {
+            Some(date) => format!("due:{}", date.format("%Y-%m-%d")),
+            None => String::from(word)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod date_parser_tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    /// 2026-10-16 is a Friday
+    #[test]
+    fn relative_dates() {
+        let today = date("2026-10-16");
+        assert_eq!(parse_date("today", today), Some(today));
+        assert_eq!(parse_date(" Tomorrow ", today), Some(date("2026-10-17")));
+        assert_eq!(parse_date("mon", today), Some(date("2026-10-19")));
+        assert_eq!(parse_date("fri", today), Some(date("2026-10-23")));
+        assert_eq!(parse_date("next monday", today), Some(date("2026-10-19")));
+        assert_eq!(parse_date("+3d", today), Some(date("2026-10-19")));
+        assert_eq!(parse_date("+3", today), Some(date("2026-10-19")));
+        assert_eq!(parse_date("+1m", today), Some(date("2026-11-16")));
+        assert_eq!(parse_date("in 2 weeks", today), Some(date("2026-10-30")));
+        assert_eq!(parse_date("in 1 year", today), Some(date("2027-10-16")));
+        assert_eq!(parse_date("eom", today), Some(date("2026-10-31")));
+        assert_eq!(parse_date("eom", date("2028-02-03")), Some(date("2028-02-29")));
+        assert_eq!(parse_date("eoy", today), Some(date("2026-12-31")));
+    }
+
+    #[test]
+    fn absolute_dates() {
+        let today = date("2026-10-16");
+        assert_eq!(parse_date("2026-12-24", today), Some(date("2026-12-24")));
+        assert_eq!(parse_date("24/12", today), Some(date("2026-12-24")));
+        assert_eq!(parse_date("16/10", today), Some(today));
+        assert_eq!(parse_date("1/2", today), Some(date("2027-02-01")));
+        assert_eq!(parse_date("24/12/2030", today), Some(date("2030-12-24")));
+    }
+
+    #[test]
+    fn invalid_dates() {
+        let today = date("2026-10-16");
+        for text in ["", "soon", "next", "in 2 fortnights", "+3x", "31/02", "2026-13-01", "next day"] {
+            assert_eq!(parse_date(text, today), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn due_tags() {
+        let today = date("2026-10-16");
+        assert_eq!(resolve_due_dates("call mom due:tomorrow +family", today), "call mom due:2026-10-17 +family");
+        assert_eq!(resolve_due_dates("pay rent due:eom", today), "pay rent due:2026-10-31");
+        assert_eq!(resolve_due_dates("a task due:someday", today), "a task due:someday");
+        assert_eq!(resolve_due_dates("a  task", today), "a  task");
+    }
+}
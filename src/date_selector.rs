@@ -1,6 +1,11 @@
-use chrono::NaiveDate;
+use std::collections::HashMap;
+use chrono::{Local, Months, NaiveDate, Weekday};
 use chrono::Datelike;
-use crate::rofi::{Rofi, RofiParams};
+use crate::rofi::{RofiError, RofiParams};
+use crate::menu::{self, Highlight};
+use crate::date_parser::parse_date;
+use crate::indexer::Indexer;
+use crate::task::Task;
 
 /// Give the number of day in a month
 /// 
@@ -28,63 +33,226 @@ fn day_in_month(month: u32, year: i32) -> u32{
     }
 }
 
-/// Open a Rofi menu to select a date.
+/// Open a Rofi menu to type a date, such as `tomorrow`, `fri` or `+3d`, and confirm it
 /// 
-/// Returns `Some(NaiveDate)` if a date is selected, `None` if the user quitted
+/// The date is picked in a calendar if it can't be understood or if the user prefers it.
+/// Returns `Some(NaiveDate)` if a date is selected, `None` if the user quitted, an error if the menu failed
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi parameters
+/// * `todos` - the tasks, their due dates being shown in the calendar
+/// * `default_date` - The date that is shown by default, relative dates being relative to it
+pub fn date_selector(rofi_config : &RofiParams, todos : &Indexer<Task>, default_date : NaiveDate) -> Result<Option<NaiveDate>, RofiError> {
+    let mut text = String::new();
+    loop {
+        let mut date_menu = menu::new(rofi_config);
+        date_menu.prompt(rofi_config.tr("prompt.date")).placeholder(rofi_config.tr("date.placeholder")).select_range(0, 0);
+        if !text.is_empty() {
+            date_menu.pretext(text.clone());
+        }
+        // The entry opening the calendar, or the year, month and day menus
+        let selection = date_menu.run(vec![String::from(rofi_config.tr("date.pick"))])?;
+        if selection.index == Some(0) {
+            return date_picker(rofi_config, todos, default_date);
+        }
+        if selection.text.is_empty() {
+            return Ok(None);
+        }
+        text = selection.text;
+        let date = match parse_date(&text, default_date) {
+            Some(date) => date,
+            None => return date_picker(rofi_config, todos, default_date)
+        };
+        // Showing the understood date before using it
+        let confirm = ["task.validate", "date.change", "task.cancel"];
+        let msg = format!("{} : {} {}", text, rofi_config.weekday_name(date.weekday()), date.format(&rofi_config.date_format));
+        let selection = menu::new(rofi_config).prompt(rofi_config.tr("prompt.date")).msg(msg).select_range(0, confirm.len()-1)
+            .run(confirm.iter().map(|k| String::from(rofi_config.tr(k))).collect())?;
+        match selection.item(0, confirm.to_vec()) {
+            Some("task.validate") => return Ok(Some(date)),
+            Some("date.change") => continue,
+            _ => return Ok(None)
+        }
+    }
+}
+
+/// Pick a date in a calendar, or in three menus for the year, the month and the day if the launcher can't show a grid
+/// 
+/// Returns `Some(NaiveDate)` if a date is selected, `None` if the user quitted, an error if the menu failed
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi parameters
+/// * `todos` - the tasks, their due dates being shown in the calendar
+/// * `default_date` - The date that is shown by default
+fn date_picker(rofi_config : &RofiParams, todos : &Indexer<Task>, default_date : NaiveDate) -> Result<Option<NaiveDate>, RofiError> {
+    if rofi_config.backend.shows_grid() {
+        calendar_picker(rofi_config, todos, default_date)
+    } else {
+        lists_picker(rofi_config, default_date)
+    }
+}
+
+/// A cell of the calendar
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cell {
+    /// The name of a weekday, at the top of its column
+    Weekday(Weekday),
+    /// A cell without a day, before the first day or after the last day of the month
+    Blank,
+    /// A day, with the number of tasks due that day
+    Day(NaiveDate, usize),
+    /// Show the previous month
+    Previous(NaiveDate),
+    /// Show the next month
+    Next(NaiveDate)
+}
+
+impl Cell {
+    /// Get the text of the cell
+    /// 
+    /// Arguments:
+    /// 
+    /// * `rofi_config` - the Rofi configuration, giving the names of the weekdays and months
+    fn label(&self, rofi_config : &RofiParams) -> String {
+        match self {
+            Cell::Weekday(day) => String::from(rofi_config.weekday(*day)),
+            Cell::Blank => String::new(),
+            Cell::Day(date, 0) => date.day().to_string(),
+            Cell::Day(date, due) => format!("{} ({})", date.day(), due),
+            Cell::Previous(month) => format!("« {}", rofi_config.month(month.month())),
+            Cell::Next(month) => format!("{} »", rofi_config.month(month.month()))
+        }
+    }
+}
+
+/// Count the tasks due each day, from the `due` index
+/// 
+/// Arguments:
+/// 
+/// * `todos` - the tasks
+fn due_tasks(todos : &Indexer<Task>) -> HashMap<NaiveDate, usize> {
+    let mut due = HashMap::new();
+    if let Some(index) = todos.index(&String::from("due")) {
+        for task in index {
+            if let Some(date) = *task.get_due() {
+                *due.entry(date).or_insert(0) += 1;
+            }
+        }
+    }
+    due
+}
+
+/// Get the cells of the calendar of a month, row by row on 7 columns
+/// 
+/// The weekdays are on the first row, and the previous and next months on the last row
+/// 
+/// Arguments:
+/// 
+/// * `first` - the first day of the month
+/// * `due` - the number of tasks due each day
+fn calendar(first : NaiveDate, due : &HashMap<NaiveDate, usize>) -> Vec<Cell> {
+    let mut cells = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun].iter().map(|d| Cell::Weekday(*d)).collect::<Vec<_>>();
+    cells.extend((0..first.weekday().num_days_from_monday()).map(|_| Cell::Blank));
+    cells.extend(first.iter_days().take(day_in_month(first.month(), first.year()) as usize).map(|date| Cell::Day(date, due.get(&date).copied().unwrap_or(0))));
+    while cells.len() % 7 != 0 {
+        cells.push(Cell::Blank);
+    }
+    let previous = first.checked_sub_months(Months::new(1)).unwrap_or(first);
+    let next = first.checked_add_months(Months::new(1)).unwrap_or(first);
+    cells.push(Cell::Previous(previous));
+    cells.extend((0..5).map(|_| Cell::Blank));
+    cells.push(Cell::Next(next));
+    cells
+}
+
+/// Open a Rofi menu showing the calendar of a month to select a date
+/// 
+/// Today is highlighted, and the number of tasks due each day is shown after the day
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi parameters
+/// * `todos` - the tasks, their due dates being shown in the calendar
+/// * `default_date` - The date that is selected by default
+fn calendar_picker(rofi_config : &RofiParams, todos : &Indexer<Task>, default_date : NaiveDate) -> Result<Option<NaiveDate>, RofiError> {
+    let today = Local::now().date_naive();
+    let due = due_tasks(todos);
+    let mut first = default_date.with_day(1).unwrap_or(default_date);
+    loop {
+        let cells = calendar(first, &due);
+        let mut calendar_menu = menu::new(rofi_config);
+        calendar_menu.prompt(rofi_config.tr("prompt.date")).msg(format!("{} {}", rofi_config.month(first.month()), first.year())).grid(7, cells.len() / 7);
+        if let Some(row) = cells.iter().position(|c| matches!(c, Cell::Day(date, _) if *date == today)) {
+            calendar_menu.highlight(&[row], Highlight::Active);
+        }
+        if let Some(row) = cells.iter().position(|c| matches!(c, Cell::Day(date, _) if *date == default_date)) {
+            calendar_menu.selected(row as u32);
+        }
+        let selection = calendar_menu.run(cells.iter().map(|c| c.label(rofi_config)).collect())?;
+        match selection.index.map(|i| cells[i]) {
+            Some(Cell::Day(date, _)) => return Ok(Some(date)),
+            Some(Cell::Previous(month)) | Some(Cell::Next(month)) => first = month,
+            Some(_) => continue,
+            None if selection.text.is_empty() => return Ok(None),
+            None => continue
+        }
+    }
+}
+
+/// Open Rofi menus to select the year, the month and the day of a date
+/// 
+/// Returns `Some(NaiveDate)` if a date is selected, `None` if the user quitted, an error if the menu failed
 /// 
 /// Arguments:
 /// 
 /// * `rofi_config` - the Rofi parameters
 /// * `default_date` - The date that is shown by default
-pub fn date_selector(rofi_config : &RofiParams, default_date : NaiveDate) -> Option<NaiveDate> {
+fn lists_picker(rofi_config : &RofiParams, default_date : NaiveDate) -> Result<Option<NaiveDate>, RofiError> {
     let now = default_date;
     let year : i32;
     let month : u32;
     let day : u32;
     let year_list : Vec<String> = (now.year()..now.year()+10).map(|x| x.to_string()).collect();
     loop {
-        let selected_year = Rofi::from(rofi_config).prompt("Year").run(year_list.clone()).unwrap();
-        if year_list.contains(&selected_year) {
-            year = year_list.iter().position(|r| r.eq(&selected_year)).unwrap() as i32 + now.year();
+        let selected_year = menu::new(rofi_config).prompt(rofi_config.tr("prompt.year")).run(year_list.clone())?;
+        if let Some(index) = selected_year.index {
+            year = index as i32 + now.year();
             break;
-        } else if selected_year.eq("") {
-            return None;
+        } else if selected_year.text.is_empty() {
+            return Ok(None);
         }
     }
-    let month_list : Vec<String> = vec!["Janvier","Février","Mars","Avril","Mai","Juin","Juillet","Août","Septembre","Octobre","Novembre","Décembre"]
-        .iter()
-        .map(|&s|String::from(s))
-        .collect();
+    let month_list : Vec<String> = (1..=12).map(|m| String::from(rofi_config.month(m))).collect();
     let suggested_month = now.month();
     loop {
-        let selected_month = Rofi::from(rofi_config).prompt("Month").selected(suggested_month-1).run(month_list.clone()).unwrap();
-        if month_list.contains(&selected_month) {
-            month = month_list.iter().position(|r| r.eq(&selected_month)).unwrap() as u32 + 1;
+        let selected_month = menu::new(rofi_config).prompt(rofi_config.tr("prompt.month")).selected(suggested_month-1).run(month_list.clone())?;
+        if let Some(index) = selected_month.index {
+            month = index as u32 + 1;
             break;
-        } else if selected_month.eq("") {
-            return None;
+        } else if selected_month.text.is_empty() {
+            return Ok(None);
         }
     }
     let day_list : Vec<String> = (1..day_in_month(month, year)+1).map(|x| x.to_string()).collect();
     let suggested_day = if month == now.month() {now.day()-1} else {0};
     loop {
-        let selected_day = Rofi::from(rofi_config).prompt("Day").selected(suggested_day).run(day_list.clone()).unwrap();
-        if day_list.contains(&selected_day) {
-            day = day_list.iter().position(|r| r.eq(&selected_day)).unwrap() as u32 + 1;
+        let selected_day = menu::new(rofi_config).prompt(rofi_config.tr("prompt.day")).selected(suggested_day).run(day_list.clone())?;
+        if let Some(index) = selected_day.index {
+            day = index as u32 + 1;
             break;
-        } else if selected_day.eq("") {
-            return None;
+        } else if selected_day.text.is_empty() {
+            return Ok(None);
         }
     }
-    let dt = NaiveDate::from_ymd(year, month, day);
-    Some(dt)
+    Ok(NaiveDate::from_ymd_opt(year, month, day))
 }
 
 #[cfg(test)]
 mod day_in_month_tests {
     use super::*;
 
-
     /// Trying to get the number of days in a month that doesn't exist
     #[test]
     #[should_panic]
@@ -119,4 +287,33 @@ mod day_in_month_tests {
         assert_eq!(day_in_month(2,2100), 28);
         assert_eq!(day_in_month(2,2400), 29);
     }
+}
+
+#[cfg(test)]
+mod calendar_tests {
+    use super::*;
+    use crate::i18n::Lang;
+
+    #[test]
+    fn calendar_cells() {
+        let first = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        let due = HashMap::from([(NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(), 2)]);
+        let cells = calendar(first, &due);
+        let labels = cells.iter().map(|c| c.label(&RofiParams::default())).collect::<Vec<_>>();
+        // October 2026 starts on a Thursday and ends on a Saturday
+        assert_eq!(labels.len(), 7 * 7);
+        assert_eq!(labels[..7], ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"]);
+        assert_eq!(labels[7..11], ["", "", "", "1"]);
+        assert_eq!(labels[26], "17 (2)");
+        assert_eq!(labels[39..42], ["30", "31", ""]);
+        assert_eq!(labels[42], "« September");
+        assert_eq!(labels[48], "November »");
+        let french = RofiParams { lang : Lang::Fr, ..RofiParams::default() };
+        assert_eq!(cells[0].label(&french), "Lu");
+        assert_eq!(cells[48].label(&french), "Novembre »");
+        // The names replaced in the configuration file are used
+        let messages = HashMap::from([(String::from("month.11"), String::from("Nov."))]);
+        assert_eq!(cells[48].label(&RofiParams { messages, ..french }), "Nov. »");
+        assert_eq!(cells[48], Cell::Next(NaiveDate::from_ymd_opt(2026, 11, 1).unwrap()));
+    }
 }
\ No newline at end of file
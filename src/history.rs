@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::indexer::Indexer;
+use crate::task::Task;
+use crate::todo_file::write_atomic;
+
+/// The maximum number of operations which can be undone
+const HISTORY_LENGTH : usize = 100;
+
+/// A change made to a task, the task being written as a todo.txt line
+#[derive(Clone, Debug, PartialEq)]
+enum Change {
+    /// A task was added
+    Add(String),
+    /// A task was removed
+    Remove(String),
+    /// A task was modified, from the first line to the second one
+    Edit(String, String)
+}
+
+impl Change {
+    /// Get the line of the task before and after the change
+    fn lines(&self) -> (Option<&String>, Option<&String>) {
+        match self {
+            Change::Add(after) => (None, Some(after)),
+            Change::Remove(before) => (Some(before), None),
+            Change::Edit(before, after) => (Some(before), Some(after))
+        }
+    }
+}
+
+/// The changes made to the tasks by a single action of the user
+type Operation = Vec<Change>;
+
+/// The todo.txt lines of the tasks, by identifier, used to find what an operation changed
+pub type Snapshot = HashMap<usize, String>;
+
+/// The history of the operations made on the tasks, which can be undone and redone
+/// 
+/// The tasks are remembered as todo.txt lines, so the history can be kept in a journal
+/// and still apply to the tasks loaded in another session
+pub struct History {
+    /// The operations which can be undone, the most recent last
+    undo : Vec<Operation>,
+    /// The undone operations which can be redone, the most recently undone last
+    redo : Vec<Operation>,
+    /// The file where the history is kept between sessions, if any
+    journal : Option<PathBuf>
+}
+
+impl History {
+    /// Create an empty history, kept in memory only
+    pub fn new() -> Self {
+        History { undo : vec![], redo : vec![], journal : None }
+    }
+
+    /// Load the history kept in a journal, which is updated with every new operation
+    /// 
+    /// The history is empty if the journal does not exist yet.
+    /// Returns the history and a warning for every malformed line, whose operation is skipped
+    /// 
+    /// Arguments:
+    /// 
+    /// * `journal` - the path of the journal
+    pub fn load(journal: PathBuf) -> Result<(Self, Vec<String>), String> {
+        let content = match fs::read_to_string(&journal) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("{}: {}", journal.display(), e))
+        };
+        let mut history = History::new();
+        let (operations, malformed) = parse_journal(&content);
+        let warnings = malformed.iter().map(|line| format!("{}: skipped the malformed journal line: {}", journal.display(), line)).collect();
+        for (redo, operation) in operations {
+            if redo {
+                history.redo.push(operation);
+            } else {
+                history.undo.push(operation);
+            }
+        }
+        history.journal = Some(journal);
+        Ok((history, warnings))
+    }
+
+    /// Remember the tasks before an operation
+    /// 
+    /// Arguments:
+    /// 
+    /// * `todos` - the tasks
+    pub fn snapshot(todos: &Indexer<Task>) -> Snapshot {
+        todos.get_main_index().iter().map(|t| (t.get_id(), t.to_todotxt())).collect()
+    }
+
+    /// Record an operation, given the tasks before and after it
+    /// 
+    /// Recording an operation clears the operations which could be redone.
+    /// Returns whether the operation changed some tasks, an operation changing nothing not being recorded
+    /// 
+    /// Arguments:
+    /// 
+    /// * `before` - the snapshot of the tasks before the operation
+    /// * `tasks` - the tasks after the operation
+    pub fn record<'a>(&mut self, mut before: Snapshot, tasks: impl IntoIterator<Item = &'a Rc<Task>>) -> Result<bool, String> {
+        let mut operation = Operation::new();
+        for task in tasks {
+            let line = task.to_todotxt();
+            match before.remove(&task.get_id()) {
+                Some(old) => if old != line {
+                    operation.push(Change::Edit(old, line));
+                },
+                None => operation.push(Change::Add(line))
+            }
+        }
+        let mut removed = before.into_iter().collect::<Vec<_>>();
+        removed.sort();
+        operation.extend(removed.into_iter().map(|(_, line)| Change::Remove(line)));
+        if operation.is_empty() {
+            return Ok(false);
+        }
+        self.undo.push(operation);
+        if self.undo.len() > HISTORY_LENGTH {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.save().map(|_| true)
+    }
+
+    /// Forget the operations which could be redone
+    pub fn clear_redo(&mut self) -> Result<(), String> {
+        if self.redo.is_empty() {
+            return Ok(());
+        }
+        self.redo.clear();
+        self.save()
+    }
+
+    /// Is there an operation to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Is there an operation to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Undo the last operation
+    /// 
+    /// If the tasks were modified since, outside RofiTodo for instance, the operation is dropped from the history
+    /// 
+    /// Arguments:
+    /// 
+    /// * `todos` - the tasks
+    /// * `add` - a closure adding a task to the indexer
+    pub fn undo(&mut self, todos: &mut Indexer<Task>, add: impl FnMut(&mut Indexer<Task>, Task) -> Rc<Task>) -> Result<(), String> {
+        let operation = match self.undo.pop() {
+            Some(operation) => operation,
+            None => return Ok(())
+        };
+        let applied = apply(todos, &operation, true, add);
+        if applied.is_ok() {
+            self.redo.push(operation);
+        }
+        self.save()?;
+        applied
+    }
+
+    /// Redo the last undone operation
+    /// 
+    /// If the tasks were modified since, outside RofiTodo for instance, the operation is dropped from the history
+    /// 
+    /// Arguments:
+    /// 
+    /// * `todos` - the tasks
+    /// * `add` - a closure adding a task to the indexer
+    pub fn redo(&mut self, todos: &mut Indexer<Task>, add: impl FnMut(&mut Indexer<Task>, Task) -> Rc<Task>) -> Result<(), String> {
+        let operation = match self.redo.pop() {
+            Some(operation) => operation,
+            None => return Ok(())
+        };
+        let applied = apply(todos, &operation, false, add);
+        if applied.is_ok() {
+            self.undo.push(operation);
+        }
+        self.save()?;
+        applied
+    }
+
+    /// Write the history to the journal, if any
+    fn save(&self) -> Result<(), String> {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return Ok(())
+        };
+        let mut content = String::new();
+        for (name, operations) in [("@undo", &self.undo), ("@redo", &self.redo)] {
+            for operation in operations {
+                content.push_str(name);
+                content.push('\n');
+                for change in operation {
+                    match change {
+                        Change::Add(after) => content.push_str(&format!("+ {}\n", after)),
+                        Change::Remove(before) => content.push_str(&format!("- {}\n", before)),
+                        Change::Edit(before, after) => content.push_str(&format!("< {}\n> {}\n", before, after))
+                    }
+                }
+            }
+        }
+        match write_atomic(journal, 0, |file| file.write_all(content.as_bytes())) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", journal.display(), e))
+        }
+    }
+}
+
+/// Apply an operation to the tasks, or revert it
+/// 
+/// The operation is applied entirely, or not at all if some tasks it changed can't be found anymore
+/// 
+/// Arguments:
+/// 
+/// * `todos` - the tasks
+/// * `operation` - the operation to apply
+/// * `revert` - revert the operation instead of applying it
+/// * `add` - a closure adding a task to the indexer
+fn apply(todos: &mut Indexer<Task>, operation: &Operation, revert: bool, mut add: impl FnMut(&mut Indexer<Task>, Task) -> Rc<Task>) -> Result<(), String> {
+    // Finding all the tasks before changing anything
+    let mut steps = Vec::<(Option<Rc<Task>>, Option<Task>)>::new();
+    for change in operation {
+        let (from, to) = match change.lines() {
+            (before, after) if revert => (after, before),
+            (before, after) => (before, after)
+        };
+        let current = match from {
+            Some(line) => {
+                let found = todos.get_main_index().iter().find(|t| {
+                    t.to_todotxt() == *line && !steps.iter().any(|(c, _)| matches!(c, Some(c) if Rc::ptr_eq(c, t)))
+                });
+                match found {
+                    Some(task) => Some(Rc::clone(task)),
+                    None => return Err(format!("The task was modified since: {}", line))
+                }
+            },
+            None => None
+        };
+        let target = match to {
+            Some(line) => Some(Task::from_todotxt(line.clone())?),
+            None => None
+        };
+        steps.push((current, target));
+    }
+    for step in steps {
+        match step {
+            (Some(current), Some(target)) => {
+                // Keeping the identity of the task, and so its position in the file
+                let mut task = todos.remove(current).expect("Some references to task were not deleted");
+                task.replace_with(target);
+                add(todos, task);
+            },
+            (Some(current), None) => {
+                todos.remove(current);
+            },
+            (None, Some(target)) => {
+                add(todos, target);
+            },
+            (None, None) => ()
+        }
+    }
+    Ok(())
+}
+
+/// Parse the content of a journal
+/// 
+/// Returns the operations in order, each one telling whether it is an operation to redo, and the malformed lines.
+/// An operation with a malformed line is skipped, as it can't be undone partly
+/// 
+/// Arguments:
+/// 
+/// * `content` - the content of the journal
+fn parse_journal(content: &str) -> (Vec<(bool, Operation)>, Vec<String>) {
+    let mut operations = Vec::<(bool, Operation)>::new();
+    let mut malformed = Vec::new();
+    // Skipping the rest of a malformed operation, until the next one
+    let mut skipping = false;
+    let mut lines = content.lines().peekable();
+    while let Some(line) = lines.next() {
+        if line == "@undo" || line == "@redo" {
+            operations.push((line == "@redo", Operation::new()));
+            skipping = false;
+            continue;
+        }
+        let change = match (line.get(..2), line.get(2..)) {
+            (Some("+ "), Some(after)) => Some(Change::Add(String::from(after))),
+            (Some("- "), Some(before)) => Some(Change::Remove(String::from(before))),
+            (Some("< "), Some(before)) => match lines.peek().and_then(|next| next.strip_prefix("> ")) {
+                Some(after) => {
+                    let change = Change::Edit(String::from(before), String::from(after));
+                    lines.next();
+                    Some(change)
+                },
+                None => None
+            },
+            _ => None
+        };
+        match (change, operations.last_mut()) {
+            _ if skipping => (),
+            (Some(change), Some((_, operation))) => operation.push(change),
+            _ => {
+                malformed.push(String::from(line));
+                // A change before any operation has no operation to skip
+                operations.pop();
+                skipping = true;
+            }
+        }
+    }
+    (operations, malformed)
+}
+
+/// Get the path of the journal of a todo.txt file (`todo.txt.journal`)
+/// 
+/// Arguments:
+/// 
+/// * `path` - the path of the todo.txt file
+pub fn journal_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".journal");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn indexer(lines: &[&str]) -> Indexer<Task> {
+        let mut todos = Indexer::<Task>::new();
+        for line in lines {
+            todos.add(Task::from_todotxt(String::from(*line)).unwrap());
+        }
+        todos
+    }
+
+    fn lines(todos: &Indexer<Task>) -> Vec<String> {
+        let mut lines = todos.get_main_index().iter().map(|t| t.to_todotxt()).collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    fn find(todos: &Indexer<Task>, content: &str) -> Rc<Task> {
+        todos.get_main_index().iter().find(|t| t.get_content() == content).unwrap().clone()
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut todos = indexer(&["a task", "b task"]);
+        let mut history = History::new();
+        assert!(!history.can_undo());
+
+        // Completing a task and adding another one in a single operation
+        let before = History::snapshot(&todos);
+        let mut a = todos.remove(find(&todos, "a task")).unwrap();
+        a.set_completed();
+        let a_id = a.get_id();
+        todos.add(a);
+        todos.add(Task::from_todotxt(String::from("c task")).unwrap());
+        history.record(before, todos.get_main_index()).unwrap();
+
+        // Removing a task
+        let before = History::snapshot(&todos);
+        todos.remove(find(&todos, "b task"));
+        history.record(before, todos.get_main_index()).unwrap();
+        assert_eq!(lines(&todos), vec!["c task", &find(&todos, "a task").to_todotxt()]);
+
+        history.undo(&mut todos, |idx, t| idx.add(t)).unwrap();
+        assert_eq!(lines(&todos).len(), 3);
+        history.undo(&mut todos, |idx, t| idx.add(t)).unwrap();
+        assert_eq!(lines(&todos), vec!["a task", "b task"]);
+        // The edited task keeps its identity
+        assert_eq!(find(&todos, "a task").get_id(), a_id);
+        assert!(!history.can_undo());
+        assert!(history.can_redo());
+
+        history.redo(&mut todos, |idx, t| idx.add(t)).unwrap();
+        assert!(find(&todos, "a task").completion);
+        assert_eq!(lines(&todos).len(), 3);
+        // An operation changing nothing isn't recorded, and keeps the operations to redo
+        assert!(!history.record(History::snapshot(&todos), todos.get_main_index()).unwrap());
+        assert!(history.can_redo());
+
+        // A new operation clears the operations to redo
+        let before = History::snapshot(&todos);
+        todos.add(Task::from_todotxt(String::from("d task")).unwrap());
+        history.record(before, todos.get_main_index()).unwrap();
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn undo_modified_tasks() {
+        let mut todos = indexer(&["a task"]);
+        let mut history = History::new();
+        let before = History::snapshot(&todos);
+        let mut a = todos.remove(find(&todos, "a task")).unwrap();
+        a.priority = Some('A');
+        todos.add(a);
+        history.record(before, todos.get_main_index()).unwrap();
+
+        // The task is modified again without being recorded, like a change made outside RofiTodo
+        let mut a = todos.remove(find(&todos, "a task")).unwrap();
+        a.priority = Some('B');
+        todos.add(a);
+
+        assert!(history.undo(&mut todos, |idx, t| idx.add(t)).is_err());
+        assert_eq!(lines(&todos), vec!["(B) a task"]);
+        assert!(!history.can_undo());
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn journal() {
+        let dir = TestDir::new("journal");
+        let journal = journal_path(&dir.join("todo.txt"));
+        assert_eq!(journal.file_name().unwrap(), "todo.txt.journal");
+
+        let mut todos = indexer(&["a task", "b task"]);
+        let (mut history, _) = History::load(journal.clone()).unwrap();
+        let before = History::snapshot(&todos);
+        let mut a = todos.remove(find(&todos, "a task")).unwrap();
+        a.set_content(String::from("a renamed task"));
+        todos.add(a);
+        todos.remove(find(&todos, "b task"));
+        todos.add(Task::from_todotxt(String::from("c task")).unwrap());
+        history.record(before, todos.get_main_index()).unwrap();
+        assert_eq!(fs::read_to_string(&journal).unwrap(), "@undo\n< a task\n> a renamed task\n+ c task\n- b task\n");
+
+        // Undoing in another session
+        let mut todos = indexer(&["a renamed task", "c task"]);
+        let (mut history, _) = History::load(journal.clone()).unwrap();
+        history.undo(&mut todos, |idx, t| idx.add(t)).unwrap();
+        assert_eq!(lines(&todos), vec!["a task", "b task"]);
+        assert_eq!(fs::read_to_string(&journal).unwrap(), "@redo\n< a task\n> a renamed task\n+ c task\n- b task\n");
+
+        // The malformed operations are skipped, the other ones are kept
+        fs::write(&journal, "+ orphan change\n@undo\n+ a task\n").unwrap();
+        let (history, warnings) = History::load(journal.clone()).unwrap();
+        assert_eq!(history.undo, vec![vec![Change::Add(String::from("a task"))]]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].ends_with("skipped the malformed journal line: + orphan change"));
+        fs::write(&journal, "@undo\n+ a task\n< edit without its new line\n- b task\n@redo\n- c task\n").unwrap();
+        let (history, warnings) = History::load(journal.clone()).unwrap();
+        assert!(history.undo.is_empty());
+        assert_eq!(history.redo, vec![vec![Change::Remove(String::from("c task"))]]);
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,339 @@
+use std::str::FromStr;
+use chrono::Weekday;
+
+/// A language of the user interface
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lang {
+    En,
+    Fr
+}
+
+/// The English texts, also used for the keys missing in the other languages
+const EN : &[(&str, &str)] = &[
+    ("menu.add", "+ add"),
+    ("menu.done", "~ done"),
+    ("menu.upcoming", "~ upcoming"),
+    ("menu.projects", "@ project tags"),
+    ("menu.contexts", "@ context tags"),
+    ("menu.search", "🔍 search"),
+    ("menu.lists", "≡ lists"),
+    ("menu.all_lists", "all lists"),
+    ("menu.undo", "↶ undo"),
+    ("menu.redo", "↷ redo"),
+    ("menu.exit", "* exit"),
+    ("menu.back", "← back"),
+    ("menu.archive", "~ archive done"),
+    ("task.complete", "✔ mark as done"),
+    ("task.uncomplete", "✔ mark as to do"),
+    ("task.cancel", "* cancel"),
+    ("task.edit", "+ edit"),
+    ("task.change_date", "+ change date"),
+    ("task.change_threshold", "+ change threshold"),
+    ("task.change_priority", "+ change priority"),
+    ("task.remove_date", "! remove date"),
+    ("task.remove_threshold", "! remove threshold"),
+    ("task.move", "→ move to list"),
+    ("task.remove", "! remove"),
+    ("task.validate", "✔ validate"),
+    ("task.add_date", "+ add date"),
+    ("prompt.todo", "Todo"),
+    ("prompt.edit", "Edit"),
+    ("prompt.task", "Task"),
+    ("prompt.done", "Done"),
+    ("prompt.tag", "Tag"),
+    ("prompt.list", "List"),
+    ("prompt.search", "Search"),
+    ("prompt.priority", "Priority"),
+    ("prompt.date", "Date"),
+    ("prompt.year", "Year"),
+    ("prompt.month", "Month"),
+    ("prompt.day", "Day"),
+    ("prompt.conflict", "Conflict"),
+    ("conflict.message", "The task list was modified outside RofiTodo"),
+    ("conflict.before", "Before"),
+    ("conflict.file", "File"),
+    ("conflict.removed", "(removed)"),
+    ("conflict.ours", "← keep RofiTodo version"),
+    ("conflict.theirs", "→ keep file version"),
+    ("conflict.both", "+ keep both"),
+    ("move.message", "Moving a task to another list can't be undone"),
+    ("recap.task", "Task"),
+    ("recap.status", "Status"),
+    ("recap.done", "Done"),
+    ("recap.todo", "To do"),
+    ("recap.priority", "Priority"),
+    ("recap.created", "Created on"),
+    ("recap.due", "Due date"),
+    ("recap.threshold", "Threshold"),
+    ("recap.contexts", "Context tags"),
+    ("recap.projects", "Project tags"),
+    ("action.complete", "mark as done"),
+    ("action.priority", "change priority"),
+    ("action.remove", "remove"),
+    ("action.edit", "edit"),
+    ("date.placeholder", "today, fri, next monday, +3d, in 2 weeks, eom, 24/12..."),
+    ("date.pick", "~ pick a date"),
+    ("date.change", "← change"),
+    ("month.1", "January"),
+    ("month.2", "February"),
+    ("month.3", "March"),
+    ("month.4", "April"),
+    ("month.5", "May"),
+    ("month.6", "June"),
+    ("month.7", "July"),
+    ("month.8", "August"),
+    ("month.9", "September"),
+    ("month.10", "October"),
+    ("month.11", "November"),
+    ("month.12", "December"),
+    ("weekday.1", "Mo"),
+    ("weekday.2", "Tu"),
+    ("weekday.3", "We"),
+    ("weekday.4", "Th"),
+    ("weekday.5", "Fr"),
+    ("weekday.6", "Sa"),
+    ("weekday.7", "Su"),
+    ("weekday.long.1", "Monday"),
+    ("weekday.long.2", "Tuesday"),
+    ("weekday.long.3", "Wednesday"),
+    ("weekday.long.4", "Thursday"),
+    ("weekday.long.5", "Friday"),
+    ("weekday.long.6", "Saturday"),
+    ("weekday.long.7", "Sunday")
+];
+
+/// The French texts
+/// 
+/// The dates typed by the user are always in English, so the placeholder isn't translated
+const FR : &[(&str, &str)] = &[
+    ("menu.add", "+ ajouter"),
+    ("menu.done", "~ terminées"),
+    ("menu.upcoming", "~ à venir"),
+    ("menu.projects", "@ projets"),
+    ("menu.contexts", "@ contextes"),
+    ("menu.search", "🔍 rechercher"),
+    ("menu.lists", "≡ listes"),
+    ("menu.all_lists", "toutes les listes"),
+    ("menu.undo", "↶ annuler"),
+    ("menu.redo", "↷ rétablir"),
+    ("menu.exit", "* quitter"),
+    ("menu.back", "← retour"),
+    ("menu.archive", "~ archiver les terminées"),
+    ("task.complete", "✔ marquer comme faite"),
+    ("task.uncomplete", "✔ marquer comme à faire"),
+    ("task.cancel", "* annuler"),
+    ("task.edit", "+ modifier"),
+    ("task.change_date", "+ changer la date"),
+    ("task.change_threshold", "+ changer le seuil"),
+    ("task.change_priority", "+ changer la priorité"),
+    ("task.remove_date", "! supprimer la date"),
+    ("task.remove_threshold", "! supprimer le seuil"),
+    ("task.move", "→ déplacer vers une liste"),
+    ("task.remove", "! supprimer"),
+    ("task.validate", "✔ valider"),
+    ("task.add_date", "+ ajouter une date"),
+    ("prompt.todo", "Tâches"),
+    ("prompt.edit", "Modifier"),
+    ("prompt.task", "Tâche"),
+    ("prompt.done", "Terminées"),
+    ("prompt.tag", "Étiquette"),
+    ("prompt.list", "Liste"),
+    ("prompt.search", "Recherche"),
+    ("prompt.priority", "Priorité"),
+    ("prompt.date", "Date"),
+    ("prompt.year", "Année"),
+    ("prompt.month", "Mois"),
+    ("prompt.day", "Jour"),
+    ("prompt.conflict", "Conflit"),
+    ("conflict.message", "La liste de tâches a été modifiée hors de RofiTodo"),
+    ("conflict.before", "Avant"),
+    ("conflict.file", "Fichier"),
+    ("conflict.removed", "(supprimée)"),
+    ("conflict.ours", "← garder la version de RofiTodo"),
+    ("conflict.theirs", "→ garder la version du fichier"),
+    ("conflict.both", "+ garder les deux"),
+    ("move.message", "Le déplacement d'une tâche vers une autre liste ne peut pas être annulé"),
+    ("recap.task", "Tâche"),
+    ("recap.status", "Statut"),
+    ("recap.done", "Faite"),
+    ("recap.todo", "À faire"),
+    ("recap.priority", "Priorité"),
+    ("recap.created", "Créée le"),
+    ("recap.due", "Échéance"),
+    ("recap.threshold", "Seuil"),
+    ("recap.contexts", "Contextes"),
+    ("recap.projects", "Projets"),
+    ("action.complete", "marquer comme faite"),
+    ("action.priority", "changer la priorité"),
+    ("action.remove", "supprimer"),
+    ("action.edit", "modifier"),
+    ("date.placeholder", "today, fri, next monday, +3d, in 2 weeks, eom, 24/12..."),
+    ("date.pick", "~ choisir une date"),
+    ("date.change", "← modifier"),
+    ("month.1", "Janvier"),
+    ("month.2", "Février"),
+    ("month.3", "Mars"),
+    ("month.4", "Avril"),
+    ("month.5", "Mai"),
+    ("month.6", "Juin"),
+    ("month.7", "Juillet"),
+    ("month.8", "Août"),
+    ("month.9", "Septembre"),
+    ("month.10", "Octobre"),
+    ("month.11", "Novembre"),
+    ("month.12", "Décembre"),
+    ("weekday.1", "Lu"),
+    ("weekday.2", "Ma"),
+    ("weekday.3", "Me"),
+    ("weekday.4", "Je"),
+    ("weekday.5", "Ve"),
+    ("weekday.6", "Sa"),
+    ("weekday.7", "Di"),
+    ("weekday.long.1", "Lundi"),
+    ("weekday.long.2", "Mardi"),
+    ("weekday.long.3", "Mercredi"),
+    ("weekday.long.4", "Jeudi"),
+    ("weekday.long.5", "Vendredi"),
+    ("weekday.long.6", "Samedi"),
+    ("weekday.long.7", "Dimanche")
+];
+
+/// The keys of the months, from January
+const MONTHS : [&str; 12] = ["month.1", "month.2", "month.3", "month.4", "month.5", "month.6", "month.7", "month.8", "month.9", "month.10", "month.11", "month.12"];
+/// The keys of the short names of the weekdays, from Monday
+const WEEKDAYS : [&str; 7] = ["weekday.1", "weekday.2", "weekday.3", "weekday.4", "weekday.5", "weekday.6", "weekday.7"];
+/// The keys of the names of the weekdays, from Monday
+const WEEKDAY_NAMES : [&str; 7] = ["weekday.long.1", "weekday.long.2", "weekday.long.3", "weekday.long.4", "weekday.long.5", "weekday.long.6", "weekday.long.7"];
+
+/// Is a key the key of a message
+/// 
+/// Arguments:
+/// 
+/// * `key` - the key, such as `menu.add`
+pub fn is_message(key: &str) -> bool {
+    EN.iter().any(|(k, _)| *k == key)
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    /// Get a language from its code, such as `fr`, or from a locale, such as `fr_FR.UTF-8`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.split(['_', '-', '.']).next().unwrap_or("");
+        match code.to_lowercase().as_ref() {
+            "en" | "c" | "posix" => Ok(Lang::En),
+            "fr" => Ok(Lang::Fr),
+            _ => Err(format!("unsupported language: {} (expected en or fr)", s))
+        }
+    }
+}
+
+impl Lang {
+    /// Get the language of the user from the locale environment variables, English if it isn't supported
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(Lang::En)
+    }
+
+    /// Get the texts of the language
+    fn catalogue(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => EN,
+            Lang::Fr => FR
+        }
+    }
+
+    /// Get the text of a message in the language
+    /// 
+    /// Returns the English text if the message isn't translated, and the key if it doesn't exist
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the message, such as `menu.add`
+    pub fn tr(&self, key: &'static str) -> &'static str {
+        let find = |catalogue: &'static [(&'static str, &'static str)]| catalogue.iter().find(|(k, _)| *k == key).map(|(_, text)| *text);
+        find(self.catalogue()).or_else(|| find(EN)).unwrap_or(key)
+    }
+
+}
+
+/// Get the key of the name of a month
+/// 
+/// Arguments:
+/// 
+/// * `month` - the month, from 1 to 12
+pub fn month_key(month: u32) -> &'static str {
+    MONTHS[(month as usize + 11) % 12]
+}
+
+/// Get the key of the short name of a weekday, shown in the calendar
+/// 
+/// Arguments:
+/// 
+/// * `day` - the weekday
+pub fn weekday_key(day: Weekday) -> &'static str {
+    WEEKDAYS[day.num_days_from_monday() as usize]
+}
+
+/// Get the key of the name of a weekday
+/// 
+/// Arguments:
+/// 
+/// * `day` - the weekday
+pub fn weekday_name_key(day: Weekday) -> &'static str {
+    WEEKDAY_NAMES[day.num_days_from_monday() as usize]
+}
+
+#[cfg(test)]
+mod i18n_tests {
+    use super::*;
+
+    const LANGS : [Lang; 2] = [Lang::En, Lang::Fr];
+
+    /// Every message must be translated in every language
+    #[test]
+    fn complete_catalogues() {
+        for lang in LANGS {
+            for other in LANGS {
+                for (key, _) in other.catalogue() {
+                    assert!(lang.catalogue().iter().any(|(k, _)| k == key), "{} is missing in {:?}", key, lang);
+                }
+            }
+        }
+        for key in MONTHS.iter().chain(WEEKDAYS.iter()).chain(WEEKDAY_NAMES.iter()) {
+            assert!(EN.iter().any(|(k, _)| k == key), "{} is missing", key);
+        }
+    }
+
+    #[test]
+    fn unique_keys() {
+        for lang in LANGS {
+            let catalogue = lang.catalogue();
+            for (i, (key, _)) in catalogue.iter().enumerate() {
+                assert!(!catalogue[i+1..].iter().any(|(k, _)| k == key), "{} is duplicated in {:?}", key, lang);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_lang() {
+        assert_eq!("fr".parse::<Lang>(), Ok(Lang::Fr));
+        assert_eq!("fr_FR.UTF-8".parse::<Lang>(), Ok(Lang::Fr));
+        assert_eq!("en_US".parse::<Lang>(), Ok(Lang::En));
+        assert_eq!("C".parse::<Lang>(), Ok(Lang::En));
+        assert!("de_DE.UTF-8".parse::<Lang>().is_err());
+    }
+
+    #[test]
+    fn translations() {
+        assert_eq!(Lang::En.tr("menu.add"), "+ add");
+        assert_eq!(Lang::Fr.tr("menu.add"), "+ ajouter");
+        assert_eq!(Lang::Fr.tr("no.such.key"), "no.such.key");
+        assert_eq!(Lang::Fr.tr(month_key(12)), "Décembre");
+        assert_eq!(Lang::En.tr(weekday_key(Weekday::Sun)), "Su");
+        assert_eq!(Lang::Fr.tr(weekday_name_key(Weekday::Mon)), "Lundi");
+    }
+}
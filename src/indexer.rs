@@ -27,7 +27,7 @@ impl<T : std::cmp::Ord> Indexer<T> {
     pub fn add(&mut self, element : T) -> Rc<T> {
         let e = Rc::new(element);
         self.main_index.insert(Rc::clone(&e));
-        for (_, index) in &mut self.indexes {
+        for index in self.indexes.values_mut() {
             index.register(Rc::clone(&e));
         }
         e
@@ -50,10 +50,7 @@ impl<T : std::cmp::Ord> Indexer<T> {
         for name in empty_indexes {
             self.remove_index(&name);
         }
-        match Rc::try_unwrap(element) {
-            Ok(task) => Some(task),
-            _ => None
-        }
+        Rc::try_unwrap(element).ok()
     }
 
     /// Get the main index to iterate over all the stored elements
@@ -70,15 +67,12 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// * `filter` - a closure to filter the elements (returns `true` if the value should be in the index)
     /// * `compare_fn` - a function to compare and sort elements
     pub fn new_index(&mut self, name : String, filter : impl Fn(&T) -> bool + 'static, compare_fn : CompareFunction<T>) {
-        match self.get_index(&name) {
-            None => {
-                let mut new_idx = Index::new(filter, compare_fn);
-                for x in &self.main_index {
-                    new_idx.register(Rc::clone(&x));
-                }
-                self.indexes.insert(name.clone(), new_idx);
-            },
-            _ => ()
+        if self.get_index(&name).is_none() {
+            let mut new_idx = Index::new(filter, compare_fn);
+            for x in &self.main_index {
+                new_idx.register(Rc::clone(x));
+            }
+            self.indexes.insert(name.clone(), new_idx);
         }
     }
 
@@ -90,15 +84,12 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// * `name` - the name of the new index
     /// * `filter` - a closure to filter the elements (returns `true` if the value should be in the index)
     pub fn new_autoremove_index(&mut self, name : String, filter : impl Fn(&T) -> bool + 'static, compare_fn : CompareFunction<T>) {
-        match self.get_index(&name) {
-            None => {
-                let mut new_idx = Index::new_autoremove(filter, compare_fn);
-                for x in &self.main_index {
-                    new_idx.register(Rc::clone(&x));
-                }
-                self.indexes.insert(name.clone(), new_idx);
-            },
-            _ => ()
+        if self.get_index(&name).is_none() {
+            let mut new_idx = Index::new_autoremove(filter, compare_fn);
+            for x in &self.main_index {
+                new_idx.register(Rc::clone(x));
+            }
+            self.indexes.insert(name.clone(), new_idx);
         }
     }
 
@@ -107,7 +98,7 @@ impl<T : std::cmp::Ord> Indexer<T> {
     /// Arguments:
     /// 
     /// * `name` - the name of the index
-    pub fn remove_index(&mut self, name: &String) -> () {
+    pub fn remove_index(&mut self, name: &String) {
         self.indexes.remove(name);
     }
 
@@ -149,7 +140,7 @@ pub struct Index<T> {
     remove_if_empty : bool
 }
 
-impl<T> Index<T> {
+impl<T : std::cmp::Ord> Index<T> {
     /// Create a new Index
     /// the `is_indexable` function is used to filter the elements
     /// the `compare` function is used to sort the task inside the index
@@ -159,7 +150,7 @@ impl<T> Index<T> {
     /// * `is_indexable` - a closure to filter the elements (returns `true` if the value should be in the index)
     /// * `compare_fn` - a function to compare and sort elements
     pub fn new(is_indexable : impl Fn(&T) -> bool + 'static, compare : CompareFunction<T>) -> Self {
-        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare : compare , remove_if_empty : false }
+        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare , remove_if_empty : false }
     }
 
     /// Create a new Index which is removed when empty
@@ -171,7 +162,7 @@ impl<T> Index<T> {
     /// * `is_indexable` - a closure to filter the elements (returns `true` if the value should be in the index)
     /// * `compare_fn` - a function to compare and sort elements
     pub fn new_autoremove(is_indexable : impl Fn(&T) -> bool + 'static, compare : CompareFunction<T>) -> Self {
-        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare : compare , remove_if_empty : true }
+        Index { content : BTreeSet::<ElementWrapper<T>>::new(), is_indexable : Box::new(is_indexable), compare , remove_if_empty : true }
     }
 
     /// Register a new element in the Index
@@ -179,7 +170,7 @@ impl<T> Index<T> {
     /// Arguments:
     /// 
     /// * `element` - a boxed element
-    pub fn register(&mut self, element : Rc<T>) -> () {
+    pub fn register(&mut self, element : Rc<T>) {
         if (self.is_indexable)(element.as_ref()) {
             let ew = ElementWrapper::new(element, self.compare);
             self.content.insert(ew);
@@ -192,7 +183,7 @@ impl<T> Index<T> {
     /// 
     /// * `element` - a reference to boxed element
     pub fn remove(&mut self, element : &Rc<T>) {
-        if (self.is_indexable)(&element) {
+        if (self.is_indexable)(element) {
             self.content.remove(&ElementWrapper::new(Rc::clone(element), self.compare));
         }
     }
@@ -239,6 +230,9 @@ impl<'a,T> Iterator for IndexIntoIterator<'a, T> {
 }
 
 /// An element of the Index which wrap the element to allow sorting
+/// 
+/// Elements considered equal by the comparison function are told apart by their own ordering,
+/// so distinct elements are never merged inside an Index
 pub struct ElementWrapper<T> {
     /// A smart pointer to the element
     content : Rc<T>,
@@ -264,29 +258,30 @@ impl<T> ElementWrapper<T> {
 }
 
 /// Implementing ordering for `ElementWrapper` to allow BTreeSet to sort it
-impl<T> Ord for ElementWrapper<T> {
+impl<T : std::cmp::Ord> Ord for ElementWrapper<T> {
     fn cmp(&self, other: &Self) -> Ordering {
         (self.compare)(self.content.as_ref(), other.content.as_ref())
+            .then_with(|| self.content.cmp(&other.content))
     }
 }
 
 /// Implementing `PartialOrd` to implement `Ord` for `ElementWrapper`
-impl<T> PartialOrd for ElementWrapper<T> {
+impl<T : std::cmp::Ord> PartialOrd for ElementWrapper<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some((self.compare)(self.content.as_ref(), other.content.as_ref()))
+        Some(self.cmp(other))
     }
 }
 
 /// Implementing `PartialEq` to implement `PartialOrd` for `ElementWrapper`
-impl<T> PartialEq for ElementWrapper<T> {
+impl<T : std::cmp::Ord> PartialEq for ElementWrapper<T> {
     fn eq(&self, other: &Self) -> bool {
         if Rc::ptr_eq(&self.content, &other.content) { return true }
-        (self.compare)(self.content.as_ref(), other.content.as_ref()) == Ordering::Equal
+        self.cmp(other) == Ordering::Equal
     }
 }
 
 /// Implementing `Eq` to implement `PartialEq` for `ElementWrapper`
-impl<T> Eq for ElementWrapper<T> { }
+impl<T : std::cmp::Ord> Eq for ElementWrapper<T> { }
 
 #[cfg(test)]
 mod index_tests {
@@ -295,15 +290,15 @@ mod index_tests {
     #[test]
     fn create_index() {
         let idx = Index::<String>::new(|_|true, String::cmp);
-        assert_eq!(idx.is_empty(), true);
-        assert_eq!(idx.remove_if_empty(), false);
+        assert!(idx.is_empty());
+        assert!(!idx.remove_if_empty());
     }
     
     #[test]
     fn create_remove_if_empty_index() {
         let idx = Index::<String>::new_autoremove(|_|true, String::cmp);
-        assert_eq!(idx.is_empty(), true);
-        assert_eq!(idx.remove_if_empty(), true);
+        assert!(idx.is_empty());
+        assert!(idx.remove_if_empty());
     }
 
     #[test]
@@ -320,7 +315,7 @@ mod index_tests {
         assert_eq!(data[0], bar);
         assert_eq!(data[1], baz);
         assert_eq!(data[2], foo);
-        assert_eq!(idx.is_empty(), false);
+        assert!(!idx.is_empty());
     }
 
     #[test]
@@ -435,7 +430,7 @@ mod indexer_tests {
 
         id.remove_index(&idxname1);
         assert!(id.index(&idxname1).is_none());
-        assert!(!id.index(&idxname2).is_none());
+        assert!(id.index(&idxname2).is_some());
         assert!(!id.index(&idxname2).unwrap().is_empty());
     }
 
@@ -450,8 +445,8 @@ mod indexer_tests {
         id.add(String::from("bar"));
         id.add(String::from("baz"));
 
-        assert!(!id.index(&idxname1).is_none());
-        assert!(!id.index(&idxname2).is_none());
+        assert!(id.index(&idxname1).is_some());
+        assert!(id.index(&idxname2).is_some());
 
         let elems = id.index(&idxname1).unwrap().into_iter().collect::<Vec<_>>();
         for elem in elems {
@@ -459,7 +454,7 @@ mod indexer_tests {
         }
 
         assert!(id.index(&idxname1).is_none());
-        assert!(!id.index(&idxname2).is_none());
+        assert!(id.index(&idxname2).is_some());
     }
 
     #[test]
@@ -494,9 +489,26 @@ mod indexer_tests {
 
         let l1 = id.index(&idxname1).unwrap().into_iter().collect::<Vec<_>>();
         let l2 = id.index(&idxname1).unwrap().into_iter().collect::<Vec<_>>();
-        let l3 = id.get_main_index().into_iter().collect::<Vec<_>>();
+        let l3 = id.get_main_index().iter().collect::<Vec<_>>();
         assert_eq!(l1.len(), 2);
         assert_eq!(l2.len(), 2);
         assert_eq!(l3.len(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn distinct_elements_with_equal_keys() {
+        let idxname = String::from("Unsorted");
+        let mut id = Indexer::<String>::new();
+        id.new_index(idxname.clone(), filt, |_, _| Ordering::Equal);
+        id.add(String::from("foo"));
+        id.add(String::from("bar"));
+        id.add(String::from("baz"));
+
+        let l = id.index(&idxname).unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(l.len(), 3);
+
+        id.remove(Rc::clone(&l[1]));
+        let l = id.index(&idxname).unwrap().into_iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        assert_eq!(l, vec!["bar", "foo"]);
+    }
+}
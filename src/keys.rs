@@ -0,0 +1,146 @@
+use crate::rofi::RofiParams;
+
+/// The highest custom keybinding number supported by Rofi
+const MAX_CUSTOM_KEYS : usize = 19;
+
+/// A quick action applied to the highlighted task of the task list
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Mark the task as done
+    Complete,
+    /// Change the priority of the task to the next one (A, B, C, none)
+    Priority,
+    /// Remove the task
+    Remove,
+    /// Edit the content of the task
+    Edit
+}
+
+impl Action {
+    /// Get an action from its name
+    /// 
+    /// Arguments:
+    /// 
+    /// * `name` - the name of the action
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_lowercase().as_ref() {
+            "complete" => Ok(Action::Complete),
+            "priority" => Ok(Action::Priority),
+            "remove" => Ok(Action::Remove),
+            "edit" => Ok(Action::Edit),
+            _ => Err(format!("unknown action: {} (expected complete, priority, remove or edit)", name))
+        }
+    }
+
+    /// Get the key of the description of the action, shown in the help text
+    fn description(&self) -> &'static str {
+        match self {
+            Action::Complete => "action.complete",
+            Action::Priority => "action.priority",
+            Action::Remove => "action.remove",
+            Action::Edit => "action.edit"
+        }
+    }
+}
+
+/// The keys of the quick actions of the task list
+/// 
+/// Each action with a key is bound to a Rofi custom keybinding (`-kb-custom-1`...)
+pub struct Keybindings {
+    /// The actions and their keys, an empty key disabling the action
+    bindings : Vec<(Action, String)>
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Keybindings { bindings : vec![
+            (Action::Complete, String::from("Alt+d")),
+            (Action::Priority, String::from("Alt+p")),
+            (Action::Remove, String::from("Alt+r")),
+            (Action::Edit, String::from("Alt+e"))
+        ] }
+    }
+}
+
+impl Keybindings {
+    /// Change the key of an action
+    /// 
+    /// Arguments:
+    /// 
+    /// * `binding` - the action and its key, such as `complete=Alt+c`, an empty key disabling the action
+    pub fn set(&mut self, binding: &str) -> Result<(), String> {
+        let (name, key) = match binding.split_once('=') {
+            Some(split) => split,
+            None => return Err(format!("malformed keybinding: {} (expected action=key)", binding))
+        };
+        let action = Action::from_name(name)?;
+        for (a, k) in &mut self.bindings {
+            if *a == action {
+                *k = String::from(key.trim());
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the actions with a key, with the number of their custom keybinding
+    pub fn active(&self) -> Vec<(usize, Action, &str)> {
+        self.bindings.iter()
+            .filter(|(_, key)| !key.is_empty())
+            .take(MAX_CUSTOM_KEYS)
+            .enumerate()
+            .map(|(n, (action, key))| (n + 1, *action, key.as_str()))
+            .collect()
+    }
+
+    /// Get the action bound to a custom keybinding
+    /// 
+    /// Arguments:
+    /// 
+    /// * `custom` - the number of the custom keybinding
+    pub fn action(&self, custom: usize) -> Option<Action> {
+        self.active().into_iter().find(|(n, _, _)| *n == custom).map(|(_, action, _)| action)
+    }
+
+    /// Get the help text listing the active keybindings
+    /// 
+    /// Arguments:
+    /// 
+    /// * `rofi_config` - the Rofi configuration, giving the descriptions of the actions
+    pub fn help(&self, rofi_config: &RofiParams) -> String {
+        self.active().iter()
+            .map(|(_, action, key)| format!("{} : {}", key, rofi_config.tr(action.description())))
+            .collect::<Vec<_>>()
+            .join("   ")
+    }
+}
+
+#[cfg(test)]
+mod keys_tests {
+    use super::*;
+    use crate::i18n::Lang;
+
+    #[test]
+    fn default_keybindings() {
+        let keys = Keybindings::default();
+        assert_eq!(keys.action(1), Some(Action::Complete));
+        assert_eq!(keys.action(4), Some(Action::Edit));
+        assert_eq!(keys.action(5), None);
+        assert_eq!(keys.help(&RofiParams::default()), "Alt+d : mark as done   Alt+p : change priority   Alt+r : remove   Alt+e : edit");
+        assert_eq!(keys.help(&RofiParams { lang : Lang::Fr, ..RofiParams::default() }), "Alt+d : marquer comme faite   Alt+p : changer la priorité   Alt+r : supprimer   Alt+e : modifier");
+        // The descriptions replaced in the configuration file are used
+        let messages = std::collections::HashMap::from([(String::from("action.remove"), String::from("delete"))]);
+        assert!(keys.help(&RofiParams { messages, ..RofiParams::default() }).contains("Alt+r : delete   "));
+    }
+
+    #[test]
+    fn change_keybindings() {
+        let mut keys = Keybindings::default();
+        keys.set("complete=Control+Return").unwrap();
+        keys.set("priority=").unwrap();
+        assert_eq!(keys.active(), vec![(1, Action::Complete, "Control+Return"), (2, Action::Remove, "Alt+r"), (3, Action::Edit, "Alt+e")]);
+        assert_eq!(keys.action(2), Some(Action::Remove));
+
+        assert!(keys.set("archive=Alt+a").is_err());
+        assert!(keys.set("Alt+a").is_err());
+    }
+}
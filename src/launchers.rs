@@ -0,0 +1,579 @@
+use std::process::Command;
+use crate::menu::{self, Highlight, Menu, Selection};
+use crate::rofi::{RofiError, RofiParams};
+
+/// The number of lines shown by the launchers showing a single line by default
+const LINES : &str = "20";
+
+/// The markers put before the highlighted rows, by the launchers which can't highlight them
+#[derive(Default)]
+struct Markers {
+    rows: Vec<(usize, Highlight)>
+}
+
+impl Markers {
+    /// Highlight some rows
+    /// 
+    /// Arguments:
+    /// 
+    /// * `rows` - the indexes of the rows
+    /// * `highlight` - how the rows are highlighted
+    fn add(&mut self, rows: &[usize], highlight: Highlight) {
+        self.rows.extend(rows.iter().map(|row| (*row, highlight)));
+    }
+
+    /// Put the markers before the entries, an urgent row being marked as urgent only
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - the entries to show
+    fn apply(&self, entries: &[String]) -> Vec<String> {
+        entries.iter().enumerate().map(|(i, entry)| {
+            let highlights = self.rows.iter().filter(|(row, _)| *row == i).map(|(_, h)| *h).collect::<Vec<_>>();
+            if highlights.contains(&Highlight::Urgent) {
+                format!("{}{}", Highlight::Urgent.marker(), entry)
+            } else if highlights.contains(&Highlight::Active) {
+                format!("{}{}", Highlight::Active.marker(), entry)
+            } else {
+                entry.clone()
+            }
+        }).collect()
+    }
+}
+
+/// Give back the entry shown without its marker as the text of a selection
+/// 
+/// Arguments:
+/// 
+/// * `selection` - the selection among the marked entries
+/// * `entries` - the entries without their markers
+fn unmark(mut selection: Selection, entries: &[String]) -> Selection {
+    if let Some(entry) = selection.index.and_then(|i| entries.get(i)) {
+        selection.text = entry.clone();
+    }
+    selection
+}
+
+/// Find the selected entry from the output of a launcher showing the entries preceded by their index
+/// 
+/// A text without the index of an entry is the typed text
+/// 
+/// Arguments:
+/// 
+/// * `output` - the text printed by the launcher
+/// * `shown` - the entries shown, without their index
+fn launcher_selection(output: String, shown: &[String]) -> Selection {
+    let index = output.split_once('\t')
+        .and_then(|(index, text)| index.parse::<usize>().ok().filter(|i| shown.get(*i).map(String::as_str) == Some(text)));
+    match index {
+        Some(i) => Selection { index : Some(i), text : shown[i].clone(), code : 0 },
+        None => Selection::from_text(output, shown, 0)
+    }
+}
+
+/// Run a launcher printing the selected entry, and find the index of the entry
+/// 
+/// Like with fzf, each entry is preceded by its index, so entries with the same text are told apart.
+/// The launcher exits with 1 when the user quits
+/// 
+/// Arguments:
+/// 
+/// * `command` - the command launching the program
+/// * `entries` - the entries to show
+/// * `markers` - the markers put before the highlighted entries
+fn run_launcher(command: &mut Command, entries: Vec<String>, markers: &Markers) -> Result<Selection, RofiError> {
+    let shown = markers.apply(&entries);
+    let indexed = shown.iter().enumerate().map(|(i, e)| format!("{}\t{}", i, e)).collect::<Vec<_>>();
+    let output = menu::launch(command, &indexed)?;
+    match menu::exit_code(output.status)? {
+        0 => Ok(unmark(launcher_selection(menu::output_text(output.stdout)?, &shown), &entries)),
+        1 => Err(RofiError::Cancelled),
+        code => Err(RofiError::Failed(code))
+    }
+}
+
+/// A menu shown by dmenu
+/// 
+/// dmenu can't show a message, pre-enter text, select a line nor mark lines as active
+pub struct Dmenu {
+    dmenu: Command,
+    markers: Markers,
+    /// Are the entries shown, on several lines
+    lines: bool
+}
+
+impl Dmenu {
+    /// Create a new dmenu instance from parameters
+    /// 
+    /// Arguments:
+    /// 
+    /// * `p` - a reference to a `RofiParams` struct
+    pub fn from(p : &RofiParams) -> Self {
+        let mut dmenu = Command::new("dmenu");
+        if p.case_insensitive {
+            dmenu.arg("-i");
+        }
+        Dmenu { dmenu, markers : Markers::default(), lines : true }
+    }
+}
+
+impl Menu for Dmenu {
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu {
+        self.dmenu.arg("-p").arg(p);
+        self
+    }
+
+    fn msg(&mut self, _m: String) -> &mut dyn Menu {
+        self
+    }
+
+    fn pretext(&mut self, _text: String) -> &mut dyn Menu {
+        self
+    }
+
+    fn selected(&mut self, _index: u32) -> &mut dyn Menu {
+        self
+    }
+
+    fn select_range(&mut self, _start: usize, _end: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu {
+        self.markers.add(rows, highlight);
+        self
+    }
+
+    fn placeholder(&mut self, _placeholder: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn text_only(&mut self) -> &mut dyn Menu {
+        self.lines = false;
+        self
+    }
+
+    fn custom_key(&mut self, _custom: usize, _key: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn markup(&mut self) -> &mut dyn Menu {
+        self
+    }
+
+    fn grid(&mut self, _columns: usize, _rows: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        if self.lines {
+            self.dmenu.arg("-l").arg(LINES);
+        }
+        run_launcher(&mut self.dmenu, entries, &self.markers)
+    }
+}
+
+/// A menu shown by bemenu
+/// 
+/// bemenu can't show a message, select a line nor mark lines as active
+pub struct Bemenu {
+    bemenu: Command,
+    markers: Markers,
+    /// Are the entries shown, on several lines
+    lines: bool
+}
+
+impl Bemenu {
+    /// Create a new bemenu instance from parameters
+    /// 
+    /// Arguments:
+    /// 
+    /// * `p` - a reference to a `RofiParams` struct
+    pub fn from(p : &RofiParams) -> Self {
+        let mut bemenu = Command::new("bemenu");
+        if p.case_insensitive {
+            bemenu.arg("-i");
+        }
+        Bemenu { bemenu, markers : Markers::default(), lines : true }
+    }
+}
+
+impl Menu for Bemenu {
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu {
+        self.bemenu.arg("-p").arg(p);
+        self
+    }
+
+    fn msg(&mut self, _m: String) -> &mut dyn Menu {
+        self
+    }
+
+    fn pretext(&mut self, text: String) -> &mut dyn Menu {
+        self.bemenu.arg("-F").arg(text);
+        self
+    }
+
+    fn selected(&mut self, _index: u32) -> &mut dyn Menu {
+        self
+    }
+
+    fn select_range(&mut self, _start: usize, _end: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu {
+        self.markers.add(rows, highlight);
+        self
+    }
+
+    fn placeholder(&mut self, _placeholder: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn text_only(&mut self) -> &mut dyn Menu {
+        self.lines = false;
+        self
+    }
+
+    fn custom_key(&mut self, _custom: usize, _key: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn markup(&mut self) -> &mut dyn Menu {
+        self
+    }
+
+    fn grid(&mut self, _columns: usize, _rows: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        if self.lines {
+            self.bemenu.arg("-l").arg(LINES);
+        }
+        run_launcher(&mut self.bemenu, entries, &self.markers)
+    }
+}
+
+/// A menu shown by wofi
+/// 
+/// wofi can't show a message, select a line nor mark lines as active, its prompt is shown as a placeholder
+pub struct Wofi {
+    wofi: Command,
+    markers: Markers
+}
+
+impl Wofi {
+    /// Create a new wofi instance from parameters
+    /// 
+    /// Arguments:
+    /// 
+    /// * `p` - a reference to a `RofiParams` struct
+    pub fn from(p : &RofiParams) -> Self {
+        let mut wofi = Command::new("wofi");
+        wofi.arg("--dmenu");
+        if p.case_insensitive {
+            wofi.arg("--insensitive");
+        }
+        Wofi { wofi, markers : Markers::default() }
+    }
+}
+
+impl Menu for Wofi {
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu {
+        self.wofi.arg("--prompt").arg(p);
+        self
+    }
+
+    fn msg(&mut self, _m: String) -> &mut dyn Menu {
+        self
+    }
+
+    fn pretext(&mut self, text: String) -> &mut dyn Menu {
+        self.wofi.arg("--search").arg(text);
+        self
+    }
+
+    fn selected(&mut self, _index: u32) -> &mut dyn Menu {
+        self
+    }
+
+    fn select_range(&mut self, _start: usize, _end: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu {
+        self.markers.add(rows, highlight);
+        self
+    }
+
+    fn placeholder(&mut self, _placeholder: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn text_only(&mut self) -> &mut dyn Menu {
+        self.wofi.arg("--lines").arg("1");
+        self
+    }
+
+    fn custom_key(&mut self, _custom: usize, _key: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn markup(&mut self) -> &mut dyn Menu {
+        self
+    }
+
+    fn grid(&mut self, _columns: usize, _rows: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        run_launcher(&mut self.wofi, entries, &self.markers)
+    }
+}
+
+/// A menu shown by fuzzel
+/// 
+/// fuzzel can't show a message, select a line nor mark lines as active, and is always case insensitive
+pub struct Fuzzel {
+    fuzzel: Command,
+    markers: Markers
+}
+
+impl Fuzzel {
+    /// Create a new fuzzel instance from parameters
+    /// 
+    /// Arguments:
+    /// 
+    /// * `p` - a reference to a `RofiParams` struct
+    pub fn from(_p : &RofiParams) -> Self {
+        let mut fuzzel = Command::new("fuzzel");
+        fuzzel.arg("--dmenu");
+        Fuzzel { fuzzel, markers : Markers::default() }
+    }
+}
+
+impl Menu for Fuzzel {
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu {
+        self.fuzzel.arg("--prompt").arg(format!("{} ", p));
+        self
+    }
+
+    fn msg(&mut self, _m: String) -> &mut dyn Menu {
+        self
+    }
+
+    fn pretext(&mut self, text: String) -> &mut dyn Menu {
+        self.fuzzel.arg("--search").arg(text);
+        self
+    }
+
+    fn selected(&mut self, _index: u32) -> &mut dyn Menu {
+        self
+    }
+
+    fn select_range(&mut self, _start: usize, _end: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu {
+        self.markers.add(rows, highlight);
+        self
+    }
+
+    fn placeholder(&mut self, placeholder: &str) -> &mut dyn Menu {
+        self.fuzzel.arg("--placeholder").arg(placeholder);
+        self
+    }
+
+    fn text_only(&mut self) -> &mut dyn Menu {
+        self.fuzzel.arg("--lines").arg("0");
+        self
+    }
+
+    fn custom_key(&mut self, _custom: usize, _key: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn markup(&mut self) -> &mut dyn Menu {
+        self
+    }
+
+    fn grid(&mut self, _columns: usize, _rows: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        run_launcher(&mut self.fuzzel, entries, &self.markers)
+    }
+}
+
+/// A menu shown by fzf, in the terminal
+/// 
+/// The message is shown as a header. fzf can't select a line nor mark lines as active
+pub struct Fzf {
+    fzf: Command,
+    markers: Markers
+}
+
+impl Fzf {
+    /// Create a new fzf instance from parameters
+    /// 
+    /// Arguments:
+    /// 
+    /// * `p` - a reference to a `RofiParams` struct
+    pub fn from(p : &RofiParams) -> Self {
+        let mut fzf = Command::new("fzf");
+        // The typed text is printed first, to be returned when it matches no entry
+        fzf.arg("--print-query").arg("--layout=reverse");
+        if p.no_config {
+            fzf.env_remove("FZF_DEFAULT_OPTS");
+        }
+        if p.case_insensitive {
+            fzf.arg("-i");
+        }
+        Fzf { fzf, markers : Markers::default() }
+    }
+}
+
+impl Menu for Fzf {
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu {
+        self.fzf.arg("--prompt").arg(format!("{} > ", p));
+        self
+    }
+
+    fn msg(&mut self, m: String) -> &mut dyn Menu {
+        self.fzf.arg("--header").arg(m);
+        self
+    }
+
+    fn pretext(&mut self, text: String) -> &mut dyn Menu {
+        self.fzf.arg("--query").arg(text);
+        self
+    }
+
+    fn selected(&mut self, _index: u32) -> &mut dyn Menu {
+        self
+    }
+
+    fn select_range(&mut self, _start: usize, _end: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu {
+        self.markers.add(rows, highlight);
+        self
+    }
+
+    fn placeholder(&mut self, _placeholder: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn text_only(&mut self) -> &mut dyn Menu {
+        self.fzf.arg("--no-info");
+        self
+    }
+
+    fn custom_key(&mut self, _custom: usize, _key: &str) -> &mut dyn Menu {
+        self
+    }
+
+    fn markup(&mut self) -> &mut dyn Menu {
+        self
+    }
+
+    fn grid(&mut self, _columns: usize, _rows: usize) -> &mut dyn Menu {
+        self
+    }
+
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        // Each entry is preceded by its index, which is hidden but printed with the selected entry
+        let indexed = self.markers.apply(&entries).iter().enumerate().map(|(i, e)| format!("{}\t{}", i, e)).collect::<Vec<_>>();
+        let output = menu::launch(self.fzf.arg("--delimiter").arg("\t").arg("--with-nth").arg("2.."), &indexed)?;
+        // Exit code 1 : the typed text matches no entry, 130 : the user quitted
+        match menu::exit_code(output.status)? {
+            code @ (0 | 1) => Ok(unmark(fzf_selection(&menu::output_text(output.stdout)?, code), &entries)),
+            130 => Err(RofiError::Cancelled),
+            code => Err(RofiError::Failed(code))
+        }
+    }
+}
+
+/// Get the selection from the output of fzf with `--print-query`
+/// 
+/// The output is the typed text, followed by the selected entry and its index if any
+/// 
+/// Arguments:
+/// 
+/// * `output` - the output of fzf
+/// * `code` - the exit code of fzf
+fn fzf_selection(output: &str, code: i32) -> Selection {
+    let mut lines = output.lines();
+    let query = lines.next().unwrap_or_default();
+    match lines.next().and_then(|line| line.split_once('\t')) {
+        Some((index, text)) => Selection { index : index.parse::<usize>().ok(), text : String::from(text), code },
+        None => Selection { index : None, text : String::from(query), code }
+    }
+}
+
+#[cfg(test)]
+mod launchers_tests {
+    use super::*;
+    use crate::menu::Backend;
+
+    fn args(command: &Command) -> Vec<&str> {
+        command.get_args().map(|a| a.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn launcher_arguments() {
+        let p = RofiParams { case_insensitive : true, backend : Backend::Dmenu, ..RofiParams::default() };
+
+        let mut dmenu = Dmenu::from(&p);
+        dmenu.prompt("Todo").msg(String::from("ignored")).selected(3);
+        assert_eq!(args(&dmenu.dmenu), vec!["-i", "-p", "Todo"]);
+
+        let mut wofi = Wofi::from(&p);
+        wofi.prompt("Task").pretext(String::from("call mom"));
+        assert_eq!(args(&wofi.wofi), vec!["--dmenu", "--insensitive", "--prompt", "Task", "--search", "call mom"]);
+
+        let mut fzf = Fzf::from(&p);
+        fzf.prompt("Edit").msg(String::from("a task"));
+        assert_eq!(args(&fzf.fzf), vec!["--print-query", "--layout=reverse", "-i", "--prompt", "Edit > ", "--header", "a task"]);
+    }
+
+    #[test]
+    fn markers() {
+        let entries = vec![String::from("+ add"), String::from("pay rent"), String::from("(A) call mom")];
+        let mut markers = Markers::default();
+        markers.add(&[1, 2], Highlight::Active);
+        markers.add(&[1], Highlight::Urgent);
+        let shown = markers.apply(&entries);
+        assert_eq!(shown, vec!["+ add", "‼ pay rent", "★ (A) call mom"]);
+
+        let selection = unmark(Selection::from_text(String::from("★ (A) call mom"), &shown, 0), &entries);
+        assert_eq!(selection, Selection { index : Some(2), text : String::from("(A) call mom"), code : 0 });
+        let typed = unmark(Selection::from_text(String::from("new task"), &shown, 0), &entries);
+        assert_eq!(typed.text, "new task");
+    }
+
+    #[test]
+    fn launcher_output() {
+        let shown = vec![String::from("+ add"), String::from("call mom"), String::from("call mom")];
+        // The entries with the same text are told apart by their index
+        assert_eq!(launcher_selection(String::from("2\tcall mom"), &shown), Selection { index : Some(2), text : String::from("call mom"), code : 0 });
+        assert_eq!(launcher_selection(String::from("1\tcall mom"), &shown).index, Some(1));
+        // The typed text is kept as is, even when it looks like an indexed entry
+        assert_eq!(launcher_selection(String::from("call dad"), &shown), Selection { index : None, text : String::from("call dad"), code : 0 });
+        assert_eq!(launcher_selection(String::from("0\tcall dad"), &shown).index, None);
+        assert_eq!(launcher_selection(String::from("+ add"), &shown).index, Some(0));
+    }
+
+    #[test]
+    fn fzf_output() {
+        assert_eq!(fzf_selection("cal\n3\tcall mom\n", 0), Selection { index : Some(3), text : String::from("call mom"), code : 0 });
+        assert_eq!(fzf_selection("new task\n", 1), Selection { index : None, text : String::from("new task"), code : 1 });
+        assert_eq!(fzf_selection("", 1), Selection { index : None, text : String::new(), code : 1 });
+    }
+}
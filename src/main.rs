@@ -1,354 +1,1236 @@
 mod rofi;
-use rofi::{Rofi, RofiParams};
+use rofi::{RofiError, RofiParams};
+mod menu;
+use menu::{Backend, Highlight, Menu, Selection};
+mod launchers;
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod test_dir;
 mod task;
-use task::{Task, SortTaskBy};
+use task::{Task, SortTaskBy, bold};
 mod date_selector;
+mod date_parser;
 use date_selector::date_selector;
-use std::fs;
-use std::io::{self, BufRead};
 use structopt::StructOpt;
-use chrono::{Local, NaiveDate, Datelike};
+use chrono::Local;
 mod indexer;
 use indexer::Indexer;
+mod recurrence;
+mod todo_file;
+use todo_file::{Changes, TodoFile, append_tasks};
+mod merge;
+use merge::{Conflict, Resolution};
+mod history;
+use history::{History, Snapshot};
+mod commands;
+use commands::Command;
+mod keys;
+use keys::{Action, Keybindings};
+mod markup;
+mod search;
+mod i18n;
+use i18n::Lang;
+mod settings;
+use settings::Settings;
+use std::collections::{BTreeSet, HashMap};
 use std::rc::Rc;
 
+/// The options of RofiTodo
+/// 
+/// The options which aren't given default to the settings of `$XDG_CONFIG_HOME/rofitodo/config.toml`
 #[derive(StructOpt)]
 struct Cli {
-    /// The path to the RofiTodo config/task list file
-    #[structopt(short, long, parse(from_os_str), default_value = "./todo.txt")]
-    config: std::path::PathBuf,
+    /// The path to the RofiTodo config/task list file [default: $TODO_FILE, $TODO_DIR/todo.txt or $XDG_DATA_HOME/rofitodo/todo.txt]
+    #[structopt(short, long, parse(from_os_str))]
+    config: Option<std::path::PathBuf>,
     /// Do not load Rofi configuration, use default values.
-    #[structopt(long = "no-config")]
+    #[structopt(long = "no-config", overrides_with = "rofi_config")]
     no_config: bool,
+    /// Load Rofi configuration, even if the configuration file disables it
+    #[structopt(long = "rofi-config", overrides_with = "no_config")]
+    rofi_config: bool,
     /// Set filter to be case insensitive
-    #[structopt(short = "i", long = "case-insensitive")]
+    #[structopt(short = "i", long = "case-insensitive", overrides_with = "case_sensitive")]
     case_insensitive: bool,
-    /// How to sort the tasks
-    #[structopt(short = "s", long="sort", possible_values = &["creation","content","priority","due"], case_insensitive = true, default_value="content")]
-    sort : String
+    /// Set filter to be case sensitive, even if the configuration file makes it case insensitive
+    #[structopt(long = "case-sensitive", overrides_with = "case_insensitive")]
+    case_sensitive: bool,
+    /// The program used to show the menus [default: rofi]
+    #[structopt(short = "b", long = "backend", possible_values = &["rofi","dmenu","wofi","fuzzel","bemenu","fzf"], case_insensitive = true)]
+    backend: Option<Backend>,
+    /// Show the tasks with Pango markup (colours for the priorities, overdue dates and tags), with Rofi only
+    #[structopt(short = "m", long = "markup", overrides_with = "no_markup")]
+    markup: bool,
+    /// Show the tasks without Pango markup, even if the configuration file enables it
+    #[structopt(long = "no-markup", overrides_with = "markup")]
+    no_markup: bool,
+    /// The language of the menus [default: from LANG]
+    #[structopt(long = "lang", possible_values = &["en","fr"], case_insensitive = true)]
+    lang: Option<Lang>,
+    /// How to sort the tasks [default: content]
+    #[structopt(short = "s", long="sort", possible_values = &["creation","content","priority","due"], case_insensitive = true)]
+    sort : Option<String>,
+    /// The number of backups of the task list file to keep, one per session [default: 3]
+    #[structopt(long = "backups")]
+    backups : Option<usize>,
+    /// Restore a backup of the task list file (1 is the most recent) and exit
+    #[structopt(long = "restore-backup", value_name = "n")]
+    restore_backup : Option<usize>,
+    /// Move the completed tasks to the archive file and exit
+    #[structopt(long = "archive")]
+    archive : bool,
+    /// The path to the archive of the completed tasks [default: done.txt next to the task list file]
+    #[structopt(long = "done-file", parse(from_os_str))]
+    done_file : Option<std::path::PathBuf>,
+    /// Keep the history of the modifications in a journal next to the task list file, to undo them in later sessions
+    #[structopt(long = "journal", overrides_with = "no_journal")]
+    journal : bool,
+    /// Don't keep the history in a journal, even if the configuration file enables it
+    #[structopt(long = "no-journal", overrides_with = "journal")]
+    no_journal : bool,
+    /// Add a task list to the list menu, such as `work=~/work.txt`
+    #[structopt(short = "l", long = "list", value_name = "name=path", number_of_values = 1)]
+    lists : Vec<String>,
+    /// Change the key of a quick action of the task list (complete, priority, remove or edit), an empty key disabling the action
+    #[structopt(short = "k", long = "key", value_name = "action=key", number_of_values = 1)]
+    keys : Vec<String>,
+    /// Work on the task list without Rofi
+    #[structopt(subcommand)]
+    command : Option<Command>
 }
 
 #[derive(PartialEq)]
 enum MenuStatus {
-    EXIT,
-    MAINMENU,
-    BACK
+    Exit,
+    MainMenu,
+    Back
 }
 
-fn show_task_menu(rofi_config : &RofiParams, params : &mut Params, task: Rc<Task>) -> MenuStatus {
+/// Create a menu showing tasks, rendering Pango markup if enabled
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+fn tasks_menu(rofi_config : &RofiParams) -> Box<dyn Menu> {
+    let mut menu = menu::new(rofi_config);
+    if rofi_config.use_markup() {
+        menu.markup();
+    }
+    menu
+}
+
+/// Get the entry of a task in a menu created by `tasks_menu`
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `task` - the task
+fn task_entry(rofi_config : &RofiParams, task : &Task) -> String {
+    if rofi_config.use_markup() {
+        markup::task_row(task, Local::now().date_naive(), &rofi_config.date_format)
+    } else {
+        task.row_str(&rofi_config.date_format)
+    }
+}
+
+/// Get the complete description of a task, shown in a menu created by `tasks_menu`
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `task` - the task
+fn task_recap(rofi_config : &RofiParams, task : &Task) -> String {
+    if rofi_config.use_markup() {
+        markup::task_recap(task, Local::now().date_naive(), rofi_config)
+    } else {
+        task.recap_str(|key| rofi_config.tr(key), &rofi_config.date_format)
+    }
+}
+
+/// Get the entries of a menu in the language of the user
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `keys` - the keys of the messages of the entries
+fn translate(rofi_config : &RofiParams, keys : &[&'static str]) -> Vec<String> {
+    keys.iter().map(|key| String::from(rofi_config.tr(key))).collect()
+}
+
+fn show_task_menu(rofi_config : &RofiParams, params : &mut Params, task: Rc<Task>) -> Result<MenuStatus, RofiError> {
     let mut updated_task = task;
     loop {
-        let mut menu =  vec![String::from("✔ mark as done"), String::from("* cancel"), String::from("+ edit"), String::from("+ change date"), String::from("+ change priority")];
-        match updated_task.get_due() {
-            Some(_) => menu.push(String::from("! remove date")),
-            None => ()
-        }
-        menu.push(String::from("! remove"));
-        match Rofi::from(rofi_config).msg(updated_task.recap_str()).select_range(0,menu.len()-1).prompt("Edit").run(menu).unwrap().as_ref() {
-            "✔ mark as done" => {
-                let mut t = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                t.set_completed();
-                add_task(&mut params.todos,t);
-                return MenuStatus::BACK;
+        // The task may have been removed from the file outside RofiTodo
+        if !params.list.todos.get_main_index().contains(&updated_task) {
+            return Ok(MenuStatus::Back);
+        }
+        let mut menu = vec!["task.complete", "task.cancel", "task.edit", "task.change_date", "task.change_threshold", "task.change_priority"];
+        if updated_task.get_due().is_some() {
+            menu.push("task.remove_date");
+        }
+        if updated_task.get_threshold().is_some() {
+            menu.push("task.remove_threshold");
+        }
+        if !params.others.is_empty() {
+            menu.push("task.move");
+        }
+        menu.push("task.remove");
+        let selection = tasks_menu(rofi_config).msg(task_recap(rofi_config, &updated_task)).select_range(0,menu.len()-1).prompt(rofi_config.tr("prompt.edit")).run(translate(rofi_config, &menu))?;
+        match selection.item(0, menu) {
+            Some("task.complete") => {
+                transaction(rofi_config, params, |todos| complete_task(todos, updated_task));
+                return Ok(MenuStatus::Back);
+            },
+            Some("task.cancel") => return Ok(MenuStatus::Back),
+            Some("task.edit") => {
+                updated_task = edit_task(rofi_config, params, updated_task)?;
+                continue;
             },
-            "* cancel" => return MenuStatus::BACK,
-            "+ edit" => {
-                let task = Rofi::from(rofi_config)
-                            .prompt("Task")
-                            .placeholder("")
-                            .pretext(updated_task.get_content().to_string())
-                            .text_only()
-                            .run(vec![])
-                            .unwrap();
-                if task.eq("") {
-                    continue;
+            Some("task.change_date") => {
+                if let Some(date) = date_selector(rofi_config, &params.list.todos, Local::now().date_naive())? {
+                    updated_task = update_task(rofi_config, params, updated_task, |t| t.set_due(Some(date)));
                 }
-                let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                old_task.set_content(task);
-                updated_task = add_task(&mut params.todos,old_task);
                 continue;
             },
-            "+ change date" => {
-                let now = Local::now();
-                match date_selector(rofi_config, NaiveDate::from_ymd(now.year(), now.month(), now.day())) {
-                    Some(date) => {
-                        let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                        old_task.set_due(Some(date));
-                        updated_task = add_task(&mut params.todos,old_task);
-                    },
-                    None => ()
+            Some("task.change_threshold") => {
+                if let Some(date) = date_selector(rofi_config, &params.list.todos, Local::now().date_naive())? {
+                    updated_task = update_task(rofi_config, params, updated_task, |t| t.set_threshold(Some(date)));
                 }
                 continue;
             },
-            "+ change priority" => {
-                match priority_selector(rofi_config) {
-                    Some(priority) => {
-                        let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                        old_task.priority = priority.chars().nth(0);
-                        updated_task = add_task(&mut params.todos,old_task);
-                    },
-                    None => ()
+            Some("task.change_priority") => {
+                if let Some(priority) = priority_selector(rofi_config)? {
+                    updated_task = update_task(rofi_config, params, updated_task, |t| t.priority = priority.chars().next());
                 }
                 continue;
             },
-            "! remove date" => {
-                let mut old_task = params.todos.remove(updated_task).expect("Some references to task were not deleted");
-                old_task.set_due(None);
-                updated_task = add_task(&mut params.todos,old_task);
+            Some("task.remove_date") => {
+                updated_task = update_task(rofi_config, params, updated_task, |t| t.set_due(None));
+                continue;
+            },
+            Some("task.remove_threshold") => {
+                updated_task = update_task(rofi_config, params, updated_task, |t| t.set_threshold(None));
+                continue;
+            },
+            Some("task.move") => {
+                if let Some(other) = list_selector(rofi_config, params)? {
+                    move_task(rofi_config, params, updated_task, other);
+                    return Ok(MenuStatus::Back);
+                }
                 continue;
             },
-            "! remove" => {
-                params.todos.remove(updated_task);
-                return MenuStatus::BACK;
+            Some("task.remove") => {
+                transaction(rofi_config, params, |todos| { todos.remove(updated_task); });
+                return Ok(MenuStatus::Back);
             },
-            _ => return MenuStatus::BACK
+            _ => return Ok(MenuStatus::Back)
+        }
+    }
+}
+
+/// Ask the user for the new content of a task
+/// 
+/// Returns the updated task, unchanged if the user quitted
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `params` - the tasks and their file
+/// * `task` - the task to edit
+fn edit_task(rofi_config : &RofiParams, params : &mut Params, task : Rc<Task>) -> Result<Rc<Task>, RofiError> {
+    let content = menu::new(rofi_config)
+                .prompt(rofi_config.tr("prompt.task"))
+                .placeholder("")
+                .pretext(task.get_content().to_string())
+                .text_only()
+                .run(vec![])?
+                .text;
+    if content.is_empty() {
+        return Ok(task);
+    }
+    Ok(update_task(rofi_config, params, task, |t| t.set_content(content)))
+}
+
+/// Apply a quick action of the task list to a task
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `params` - the tasks and their file
+/// * `action` - the action bound to the key used by the user
+/// * `task` - the highlighted task
+fn quick_action(rofi_config : &RofiParams, params : &mut Params, action : Action, task : Rc<Task>) -> Result<(), RofiError> {
+    match action {
+        Action::Complete => {
+            transaction(rofi_config, params, |todos| { complete_task(todos, task); });
+        },
+        Action::Priority => {
+            update_task(rofi_config, params, task, |t| t.priority = next_priority(t.priority));
+        },
+        Action::Remove => {
+            transaction(rofi_config, params, |todos| { todos.remove(task); });
+        },
+        Action::Edit => {
+            edit_task(rofi_config, params, task)?;
         }
     }
+    Ok(())
 }
 
-fn priority_selector(rofi_config : &RofiParams) -> Option<String> {
+/// Get the priority following a priority, cycling through A, B, C and no priority
+/// 
+/// Arguments:
+/// 
+/// * `priority` - the current priority
+fn next_priority(priority : Option<char>) -> Option<char> {
+    match priority {
+        None => Some('A'),
+        Some('A') => Some('B'),
+        Some('B') => Some('C'),
+        Some(_) => None
+    }
+}
+
+fn priority_selector(rofi_config : &RofiParams) -> Result<Option<String>, RofiError> {
     let alpha = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars();
     let priority_list : Vec<String> = alpha.map(|x| x.to_string()).collect();
     loop {
-        let selected_priority = Rofi::from(rofi_config).prompt("Year").run(priority_list.clone()).unwrap();
-        if priority_list.contains(&selected_priority) {
-            return Some(selected_priority);
-        } else if selected_priority.eq("") {
-            return None;
+        let selected_priority = menu::new(rofi_config).prompt(rofi_config.tr("prompt.priority")).run(priority_list.clone())?;
+        if let Some(index) = selected_priority.index {
+            return Ok(Some(priority_list[index].clone()));
+        } else if selected_priority.text.is_empty() {
+            return Ok(None);
         }
     }
 }
 
 
-fn show_done_task_menu(rofi_config : &RofiParams, params : &mut Params, task: Rc<Task>) -> MenuStatus {
-    loop {
-        let menu =  vec![String::from("✔ mark as to do"),String::from("! remove"),String::from("* cancel")];
-        match Rofi::from(rofi_config).msg(task.recap_str()).select_range(0,menu.len()-1).prompt("Edit").run(menu).unwrap().as_ref() {
-            "✔ mark as to do" => {
-                let mut t = params.todos.remove(task).expect("Some references to task were not deleted");
-                t.set_not_completed();
-                add_task(&mut params.todos,t);
-                return MenuStatus::BACK;
-            },
-            "* cancel" => return MenuStatus::BACK,
-            "! remove" => {
-                params.todos.remove(task);
-                return MenuStatus::BACK;
-            },
-            _ => return MenuStatus::EXIT
-        }
+fn show_done_task_menu(rofi_config : &RofiParams, params : &mut Params, task: Rc<Task>) -> Result<MenuStatus, RofiError> {
+    let menu = vec!["task.uncomplete", "task.remove", "task.cancel"];
+    let selection = tasks_menu(rofi_config).msg(task_recap(rofi_config, &task)).select_range(0,menu.len()-1).prompt(rofi_config.tr("prompt.edit")).run(translate(rofi_config, &menu))?;
+    match selection.item(0, menu) {
+        Some("task.uncomplete") => {
+            update_task(rofi_config, params, task, |t| t.set_not_completed());
+            Ok(MenuStatus::Back)
+        },
+        Some("task.cancel") => Ok(MenuStatus::Back),
+        Some("task.remove") => {
+            transaction(rofi_config, params, |todos| { todos.remove(task); });
+            Ok(MenuStatus::Back)
+        },
+        _ => Ok(MenuStatus::Exit)
     }
 }
 
-fn show_add_task(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
-    let task = Rofi::from(rofi_config).prompt("Task").placeholder("").text_only().run(vec![]).unwrap();
-    if task.eq("") {
-        return MenuStatus::MAINMENU;
+fn show_add_task(rofi_config : &RofiParams, params : &mut Params) -> Result<MenuStatus, RofiError> {
+    let task = menu::new(rofi_config).prompt(rofi_config.tr("prompt.task")).placeholder("").text_only().run(vec![])?.text;
+    if task.is_empty() {
+        return Ok(MenuStatus::MainMenu);
     }
-    let menu =  vec![String::from("✔ validate"), String::from("+ add date"), String::from("* cancel")];
-    match Rofi::from(rofi_config).prompt("Edit").select_range(0,menu.len()-1).run(menu).unwrap().as_ref() {
-        "✔ validate" => {
-            add_task(&mut params.todos,Task::new(task));
-            MenuStatus::MAINMENU
+    // Due dates can be typed like in the date menu, such as "due:tomorrow"
+    let task = date_parser::resolve_due_dates(&task, Local::now().date_naive());
+    let menu = vec!["task.validate", "task.add_date", "task.cancel"];
+    let selection = menu::new(rofi_config).prompt(rofi_config.tr("prompt.edit")).select_range(0,menu.len()-1).run(translate(rofi_config, &menu))?;
+    match selection.item(0, menu) {
+        Some("task.validate") => {
+            transaction(rofi_config, params, |todos| add_task(todos,Task::new(task)));
+            Ok(MenuStatus::MainMenu)
         },
-        "* cancel" => MenuStatus::MAINMENU,
-        "+ add date" => {
-            let now = Local::now();
-            match date_selector(rofi_config, NaiveDate::from_ymd(now.year(), now.month(), now.day())) {
-                Some(date) => {add_task(&mut params.todos,Task::new_with_date(task, date));},
-                None => ()
+        Some("task.cancel") => Ok(MenuStatus::MainMenu),
+        Some("task.add_date") => {
+            if let Some(date) = date_selector(rofi_config, &params.list.todos, Local::now().date_naive())? {
+                transaction(rofi_config, params, |todos| add_task(todos,Task::new_with_date(task, date)));
             }
-            MenuStatus::MAINMENU
+            Ok(MenuStatus::MainMenu)
         },
-        _ => MenuStatus::EXIT
+        _ => Ok(MenuStatus::Exit)
     }
 }
 
-fn show_old_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+/// Ask the user for a query and show the matching tasks, open or done, the best matches first
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `params` - the tasks
+fn show_search(rofi_config : &RofiParams, params : &mut Params) -> Result<MenuStatus, RofiError> {
+    let query = menu::new(rofi_config).prompt(rofi_config.tr("prompt.search")).placeholder("").text_only().run(vec![])?.text;
+    if query.trim().is_empty() {
+        return Ok(MenuStatus::MainMenu);
+    }
     loop {
-        let mut choices =  vec![String::from("← back"), String::from("* exit")];
-        for todo in params.todos.index(&String::from("done")).unwrap() {
-            choices.push(todo.to_string());
-        }
-        match Rofi::from(rofi_config).prompt("Done").select_range(0,1).run(choices).unwrap().as_ref() {
-            "← back" => return MenuStatus::BACK,
-            "* exit" => return MenuStatus::EXIT,
-            "" => return MenuStatus::EXIT,
-            s => {
-                let result = params.todos.index(&String::from("done")).unwrap().into_iter().find(|x| x.to_string().eq(s));
-                if let None = result {
+        let keys = vec!["menu.back"];
+        let entries = keys.len();
+        let mut choices = translate(rofi_config, &keys);
+        let tasks = search::search(&query, params.list.todos.get_main_index());
+        choices.extend(tasks.iter().map(|t| task_entry(rofi_config, t)));
+        let selection = tasks_menu(rofi_config).prompt(rofi_config.tr("prompt.search")).select_range(0,entries-1).run(choices)?;
+        let status = match selection.item(entries, tasks) {
+            Some(task) if task.completion => show_done_task_menu(rofi_config, params, task)?,
+            Some(task) => show_task_menu(rofi_config, params, task)?,
+            None => match selection.item(0, keys) {
+                Some("menu.back") => return Ok(MenuStatus::Back),
+                None if selection.text.is_empty() => return Ok(MenuStatus::Exit),
+                _ => continue
+            }
+        };
+        match status {
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return Ok(MenuStatus::Exit),
+            MenuStatus::MainMenu => return Ok(MenuStatus::MainMenu)
+        }
+    }
+}
+
+fn show_old_menu(rofi_config : &RofiParams, params : &mut Params) -> Result<MenuStatus, RofiError> {
+    loop {
+        let keys = vec!["menu.back", "menu.archive", "menu.exit"];
+        let entries = keys.len();
+        let mut choices = translate(rofi_config, &keys);
+        let tasks = params.list.todos.index(&String::from("done")).unwrap().into_iter().collect::<Vec<Rc<Task>>>();
+        choices.extend(tasks.iter().map(|t| task_entry(rofi_config, t)));
+        let selection = tasks_menu(rofi_config).prompt(rofi_config.tr("prompt.done")).select_range(0,entries-1).run(choices)?;
+        if let Some(task) = selection.item(entries, tasks) {
+            match show_done_task_menu(rofi_config, params, task)? {
+                MenuStatus::Back => continue,
+                MenuStatus::Exit => return Ok(MenuStatus::Exit),
+                MenuStatus::MainMenu => return Ok(MenuStatus::MainMenu)
+            }
+        }
+        match selection.item(0, keys) {
+            Some("menu.back") => return Ok(MenuStatus::Back),
+            Some("menu.archive") => match archive_done(rofi_config, params) {
+                Ok(_) => return Ok(MenuStatus::MainMenu),
+                Err(s) => {
+                    eprintln!("{}", s);
+                    params.failed = true;
                     continue
                 }
-                match show_done_task_menu(rofi_config, params, result.unwrap()) {
-                    MenuStatus::BACK => continue,
-                    MenuStatus::EXIT => return MenuStatus::EXIT,
-                    MenuStatus::MAINMENU => return MenuStatus::MAINMENU
-                }
-            }
+            },
+            Some("menu.exit") => return Ok(MenuStatus::Exit),
+            None if selection.text.is_empty() => return Ok(MenuStatus::Exit),
+            _ => continue
         }
     }
 }
 
-fn show_tags_menu(rofi_config : &RofiParams, params : &mut Params, index_name: String) -> MenuStatus {
+fn show_tags_menu(rofi_config : &RofiParams, params : &mut Params, index_name: String) -> Result<MenuStatus, RofiError> {
     loop {
-        let mut choices = vec![String::from("← back")];
+        let mut choices = translate(rofi_config, &["menu.back"]);
+        let entries = choices.len();
         // Exiting if the index was removed
-        let idx = match params.todos.index(&index_name) {
-            Some(index) => index,
-            None => return MenuStatus::BACK
+        let tasks = match params.list.todos.index(&index_name) {
+            Some(index) => index.into_iter().collect::<Vec<Rc<Task>>>(),
+            None => return Ok(MenuStatus::Back)
         };
-        for todo in idx {
-            choices.push(todo.to_string());
-        }
-        let status : MenuStatus = match Rofi::from(rofi_config).prompt("Todo").select_range(0,0).run(choices).unwrap().as_ref() {
-            "← back" => MenuStatus::MAINMENU,
-            "" => MenuStatus::EXIT,
-            s => {
-                let result = params.todos.index(&index_name).unwrap().into_iter().find(|x| x.to_string().eq(s));
-                match result {
-                    Some(t) => show_task_menu(rofi_config, params, t),
-                    None => MenuStatus::MAINMENU
-                }
-            }
+        choices.extend(tasks.iter().map(|t| task_entry(rofi_config, t)));
+        let selection = tasks_menu(rofi_config).prompt(rofi_config.tr("prompt.todo")).select_range(0,entries-1).run(choices)?;
+        let status : MenuStatus = match selection.item(entries, tasks) {
+            Some(t) => show_task_menu(rofi_config, params, t)?,
+            None if selection.text.is_empty() => MenuStatus::Exit,
+            None => MenuStatus::MainMenu
         };
         match status {
-            MenuStatus::BACK => continue,
-            MenuStatus::EXIT => return MenuStatus::EXIT,
-            MenuStatus::MAINMENU => return MenuStatus::MAINMENU
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return Ok(MenuStatus::Exit),
+            MenuStatus::MainMenu => return Ok(MenuStatus::MainMenu)
         }
     }
 }
 
-fn show_tag_list(rofi_config : &RofiParams, params : &mut Params, tag_type: String) -> MenuStatus {
+fn show_tag_list(rofi_config : &RofiParams, params : &mut Params, tag_type: String) -> Result<MenuStatus, RofiError> {
     loop {
-        let mut choices = vec![String::from("← back")];
-        let tags = params.todos.get_index_list()
+        let mut choices = translate(rofi_config, &["menu.back"]);
+        let mut tags = params.list.todos.get_index_list()
                                 .iter()
                                 .filter(|x|x.starts_with(&tag_type))
                                 .map(|x|{let mut s = String::from(*x); s.replace_range(0..tag_type.len(), ""); s})
                                 .collect::<Vec<String>>();
-        for tag in tags {
-            choices.push(tag.to_string());
-        }
-        let status : MenuStatus = match Rofi::from(rofi_config).prompt("Tag").select_range(0,0).run(choices).unwrap().as_ref() {
-            "← back" => MenuStatus::MAINMENU,
-            "" => MenuStatus::EXIT,
-            s => {
-                let mut idx_name = tag_type.to_string();
-                idx_name.push_str(s);
-                let result = params.todos.index(&idx_name);
-                match result {
-                    Some(_) => show_tags_menu(rofi_config, params, idx_name),
-                    None => MenuStatus::BACK
-                }
+        // The indexes are not ordered
+        tags.sort();
+        choices.extend(tags.iter().cloned());
+        let selection = menu::new(rofi_config).prompt(rofi_config.tr("prompt.tag")).select_range(0,0).run(choices)?;
+        // Tags are found by the index of their entry, the text typed by the user may not be a tag
+        let status : MenuStatus = match selection.item(1, tags) {
+            Some(tag) => show_tags_menu(rofi_config, params, format!("{}{}", tag_type, tag))?,
+            None => match selection.index {
+                Some(0) => MenuStatus::MainMenu,
+                None if selection.text.is_empty() => MenuStatus::Exit,
+                _ => MenuStatus::Back
             }
         };
         match status {
-            MenuStatus::BACK => continue,
-            MenuStatus::EXIT => return MenuStatus::EXIT,
-            MenuStatus::MAINMENU => return MenuStatus::MAINMENU
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return Ok(MenuStatus::Exit),
+            MenuStatus::MainMenu => return Ok(MenuStatus::MainMenu)
         }
     }
 }
 
-fn show_main_menu(rofi_config : &RofiParams, params : &mut Params) -> MenuStatus {
+/// Count the tasks to do
+/// 
+/// Arguments:
+/// 
+/// * `tasks` - the tasks
+fn open_tasks<'a>(tasks : impl IntoIterator<Item = &'a Rc<Task>>) -> usize {
+    tasks.into_iter().filter(|t| !t.completion).count()
+}
+
+/// Ask the user to choose the list to move a task to, among the lists not shown
+/// 
+/// The message tells that the move can't be undone.
+/// Returns the index of the list among the other lists, `None` if the user quitted
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `params` - the task lists
+fn list_selector(rofi_config : &RofiParams, params : &Params) -> Result<Option<usize>, RofiError> {
+    let mut lists = params.others.iter().enumerate().collect::<Vec<_>>();
+    lists.sort_by_key(|(_, list)| list.position);
+    let mut choices = translate(rofi_config, &["menu.back"]);
+    let entries = choices.len();
+    choices.extend(lists.iter().map(|(_, list)| format!("{} ({})", list.name, open_tasks(list.todos.get_main_index()))));
+    let selection = menu::new(rofi_config).msg(String::from(rofi_config.tr("move.message"))).prompt(rofi_config.tr("prompt.list")).select_range(0,entries-1).run(choices)?;
+    Ok(selection.item(entries, lists).map(|(other, _)| other))
+}
+
+/// Show the task lists, with their number of tasks to do, to switch the list shown in the menus
+/// 
+/// The last entry shows the tasks of every list together, in the combined view
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration
+/// * `params` - the task lists
+fn show_lists_menu(rofi_config : &RofiParams, params : &mut Params) -> Result<MenuStatus, RofiError> {
+    let lists = params.lists();
+    let combined = !params.list.merged.is_empty();
+    let mut choices = translate(rofi_config, &["menu.back"]);
+    let entries = choices.len();
+    choices.extend(lists.iter().map(|(list, open)| format!("{} ({})", list.name, open)));
+    choices.push(format!("{} ({})", rofi_config.tr("menu.all_lists"), lists.iter().map(|(_, open)| open).sum::<usize>()));
+    // The shown list is highlighted, the combined view being the last entry
+    let shown = if combined { lists.len() } else { lists.iter().position(|(list, _)| list.position == params.list.position).unwrap_or(0) };
+    let mut positions = lists.iter().map(|(list, _)| Some(list.position)).collect::<Vec<_>>();
+    positions.push(None);
+    let selection = menu::new(rofi_config).prompt(rofi_config.tr("prompt.list")).select_range(0,entries-1).highlight(&[entries + shown], Highlight::Active).run(choices)?;
+    match selection.item(entries, positions) {
+        Some(Some(position)) => params.show(position),
+        Some(None) => params.combine(String::from(rofi_config.tr("menu.all_lists"))),
+        None => ()
+    }
+    Ok(MenuStatus::MainMenu)
+}
+
+fn show_main_menu(rofi_config : &RofiParams, params : &mut Params) -> Result<MenuStatus, RofiError> {
+    // The row highlighted after a quick action, to apply several actions in a row
+    let mut row = None;
+    // The failure of the last action, shown above the tasks
+    let mut error = None;
     loop {
-        let mut choices = vec![String::from("+ add"), String::from("~ done"), String::from("@ project tags"), String::from("@ context tags") , String::from("* exit")];
-        for todo in params.todos.index(&params.get_sort_string()).unwrap() {
-            choices.push(todo.to_string());
+        let mut keys = vec!["menu.add", "menu.done", "menu.upcoming", "menu.projects", "menu.contexts", "menu.search"];
+        if params.has_lists() {
+            keys.push("menu.lists");
         }
-        let status : MenuStatus = match Rofi::from(rofi_config).prompt("Todo").select_range(0,4).run(choices).unwrap().as_ref() {
-            "+ add" => {
-                show_add_task(rofi_config, params)
-            },
-            "~ done" => {
-                show_old_menu(rofi_config, params)
-            },
-            "@ project tags" => {
-                show_tag_list(rofi_config, params, String::from("project_"))
-            },
-            "@ context tags" => {
-                show_tag_list(rofi_config, params, String::from("context_"))
-            },
-            "* exit" => MenuStatus::EXIT,
-            "" => MenuStatus::EXIT,
-            s => {
-                let result = params.todos.index(&params.get_sort_string()).unwrap().into_iter().find(|x| x.to_string().eq(s));
-                match result {
-                    Some(t) => show_task_menu(rofi_config, params, t),
-                    None => MenuStatus::MAINMENU
-                }
+        if params.list.can_undo() {
+            keys.push("menu.undo");
+        }
+        if params.list.can_redo() {
+            keys.push("menu.redo");
+        }
+        keys.push("menu.exit");
+        let entries = keys.len();
+        let mut choices = translate(rofi_config, &keys);
+        let tasks = params.list.todos.index(&params.get_sort_string()).unwrap().into_iter().collect::<Vec<Rc<Task>>>();
+        choices.extend(tasks.iter().map(|t| task_entry(rofi_config, t)));
+        // Overdue tasks are urgent and tasks of priority A are active, the tasks being listed after the entries
+        let today = Local::now().date_naive();
+        let overdue = tasks.iter().enumerate().filter(|(_, t)| t.is_overdue(today)).map(|(i, _)| entries + i).collect::<Vec<_>>();
+        let important = tasks.iter().enumerate().filter(|(_, t)| t.priority == Some('A')).map(|(i, _)| entries + i).collect::<Vec<_>>();
+        let mut menu = tasks_menu(rofi_config);
+        // The name of the shown list is only useful with several lists
+        let prompt = if !params.has_lists() {
+            String::from(rofi_config.tr("prompt.todo"))
+        } else {
+            format!("{} ({})", rofi_config.tr("prompt.todo"), params.list.name)
+        };
+        menu.prompt(&prompt).select_range(0,entries-1).highlight(&overdue, Highlight::Urgent).highlight(&important, Highlight::Active);
+        for (custom, _, key) in params.keys.active() {
+            menu.custom_key(custom, key);
+        }
+        let help = params.keys.help(rofi_config);
+        let msg = error.take().into_iter().chain(Some(help).filter(|help| !help.is_empty())).collect::<Vec<String>>().join("\n");
+        if !msg.is_empty() {
+            menu.msg(if rofi_config.use_markup() { markup::escape(&msg) } else { msg });
+        }
+        if let Some(row) = row {
+            menu.selected(row as u32);
+        }
+        let selection = menu.run(choices)?;
+        row = None;
+        if let Some(action) = selection.custom_key().and_then(|custom| params.keys.action(custom)) {
+            // A quick action only applies to a task, the key is ignored on the other entries
+            row = selection.index;
+            if let Some(task) = selection.item(entries, tasks) {
+                quick_action(rofi_config, params, action, task)?;
+            }
+            continue;
+        }
+        // Tasks are found by the index of their entry, their text may not be unique
+        let status : MenuStatus = match selection.item(entries, tasks) {
+            Some(t) => show_task_menu(rofi_config, params, t)?,
+            None => match selection.item(0, keys) {
+                Some("menu.add") => {
+                    show_add_task(rofi_config, params)?
+                },
+                Some("menu.done") => {
+                    show_old_menu(rofi_config, params)?
+                },
+                Some("menu.upcoming") => {
+                    show_tags_menu(rofi_config, params, String::from("upcoming"))?
+                },
+                Some("menu.projects") => {
+                    show_tag_list(rofi_config, params, String::from("project_"))?
+                },
+                Some("menu.contexts") => {
+                    show_tag_list(rofi_config, params, String::from("context_"))?
+                },
+                Some("menu.search") => {
+                    show_search(rofi_config, params)?
+                },
+                Some("menu.lists") => {
+                    show_lists_menu(rofi_config, params)?
+                },
+                Some("menu.undo") => {
+                    if let Err(s) = params.list.undo() {
+                        error = Some(s);
+                    }
+                    save(rofi_config, params);
+                    MenuStatus::MainMenu
+                },
+                Some("menu.redo") => {
+                    if let Err(s) = params.list.redo() {
+                        error = Some(s);
+                    }
+                    save(rofi_config, params);
+                    MenuStatus::MainMenu
+                },
+                Some("menu.exit") => MenuStatus::Exit,
+                None if selection.text.is_empty() => MenuStatus::Exit,
+                _ => MenuStatus::MainMenu
             }
         };
         match status {
-            MenuStatus::BACK => continue,
-            MenuStatus::EXIT => return MenuStatus::EXIT,
-            MenuStatus::MAINMENU => continue
+            MenuStatus::Back => continue,
+            MenuStatus::Exit => return Ok(MenuStatus::Exit),
+            MenuStatus::MainMenu => continue
         }
     }
 }
 
-fn load_config(config_file: &std::path::PathBuf, todos: &mut Indexer<Task>) -> Result<bool, String> {
-    if !std::path::Path::new(config_file).exists() {
-        save_config(config_file, todos).unwrap();
+/// Load the tasks of a todo.txt file
+/// 
+/// Return the layout of the file, used to save it
+fn load_config(config_file: &std::path::Path, todos: &mut Indexer<Task>) -> Result<TodoFile, String> {
+    let (file, tasks) = TodoFile::load(config_file.to_path_buf())?;
+    for task in tasks {
+        add_task(todos, task);
     }
-    if let Ok(lines) = read_lines(config_file) {
-        for line in lines {
-            if let Ok(linestr) = line {
-                let task_result = Task::from_todotxt(String::from(linestr));
-                match task_result {
-                    Ok(task) => {add_task(todos, task);}
-                    Err(_) => ()
-                }
-            }
+    Ok(file)
+}
+
+/// Save the tasks to the todo.txt file
+/// 
+/// The modifications made to the file outside RofiTodo are merged into the tasks,
+/// conflicts are resolved by the user
+fn save_config(rofi_config : &RofiParams, file : &mut TodoFile, todos : &mut Indexer<Task>) -> Result<(), String> {
+    let changes = file.save(todos.get_main_index(), |conflict| conflict_selector(rofi_config, conflict))?;
+    apply_changes(todos, changes);
+    Ok(())
+}
+
+/// Apply to the tasks the changes made when merging the modifications made to a file outside RofiTodo
+/// 
+/// Returns the added tasks
+/// 
+/// Arguments:
+/// 
+/// * `todos` - the tasks
+/// * `changes` - the changes returned by saving the file
+fn apply_changes(todos : &mut Indexer<Task>, changes : Changes) -> Vec<Rc<Task>> {
+    for id in changes.removed {
+        let removed = todos.get_main_index().iter().find(|t| t.get_id() == id).cloned();
+        if let Some(task) = removed {
+            todos.remove(task);
+        }
+    }
+    changes.added.into_iter().map(|task| add_task(todos, task)).collect()
+}
+
+/// Save the tasks of a task list
+/// 
+/// In the combined view, every task is saved to the file of its list, the new tasks being added to the main list
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration, used to resolve conflicts
+/// * `list` - the task list
+fn save_list(rofi_config : &RofiParams, list : &mut TaskList) -> Result<(), String> {
+    if list.merged.is_empty() {
+        return save_config(rofi_config, &mut list.file, &mut list.todos);
+    }
+    for task in list.todos.get_main_index() {
+        let origin = list.origin(task);
+        list.origins.insert(task.get_id(), origin);
+    }
+    let TaskList { todos, merged, origins, saved, .. } = list;
+    let mut errors = Vec::new();
+    for (origin, merged) in merged.iter_mut().enumerate() {
+        let tasks = todos.get_main_index().iter().filter(|t| origins[&t.get_id()] == origin).cloned().collect::<BTreeSet<_>>();
+        let changes = merged.file.save(&tasks, |conflict| conflict_selector(rofi_config, conflict));
+        // The tasks are only removed from the indexer once the set doesn't reference them anymore
+        drop(tasks);
+        match changes {
+            Ok(changes) => for task in apply_changes(todos, changes) {
+                origins.insert(task.get_id(), origin);
+            },
+            Err(s) => errors.push(s)
+        }
+    }
+    for task in todos.get_main_index() {
+        saved.insert(task.to_todotxt(), origins[&task.get_id()]);
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+}
+
+/// Apply a modification to the tasks and save them immediately
+/// 
+/// Every modification made from the menus goes through this function, so the file is always up to date
+/// and the modification can be undone
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration, used to resolve conflicts
+/// * `params` - the tasks and their file
+/// * `modify` - the modification to apply to the tasks
+fn transaction<R>(rofi_config : &RofiParams, params : &mut Params, modify : impl FnOnce(&mut Indexer<Task>) -> R) -> R {
+    let result = record_operation(params, modify);
+    save(rofi_config, params);
+    result
+}
+
+/// Apply a modification to the tasks and record it in the history, without saving them
+/// 
+/// Arguments:
+/// 
+/// * `params` - the tasks and their history
+/// * `modify` - the modification to apply to the tasks
+fn record_operation<R>(params : &mut Params, modify : impl FnOnce(&mut Indexer<Task>) -> R) -> R {
+    let before = History::snapshot(&params.list.todos);
+    let result = modify(&mut params.list.todos);
+    if let Err(s) = params.list.record(before) {
+        eprintln!("{}", s);
+    }
+    result
+}
+
+/// Save the tasks immediately
+/// 
+/// A failed save is reported on the error output and retried with the next one, RofiTodo exiting with an error
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration, used to resolve conflicts
+/// * `params` - the tasks and their file
+fn save(rofi_config : &RofiParams, params : &mut Params) {
+    if let Err(s) = save_list(rofi_config, &mut params.list) {
+        eprintln!("{}", s);
+        params.failed = true;
+    }
+}
+
+/// Save the tasks of every task list
+/// 
+/// A failed save is reported on the error output, the other lists being saved anyway
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration, used to resolve conflicts
+/// * `params` - the task lists
+fn save_lists(rofi_config : &RofiParams, params : &mut Params) {
+    for list in std::iter::once(&mut params.list).chain(params.others.iter_mut()) {
+        if let Err(s) = save_list(rofi_config, list) {
+            eprintln!("{}", s);
+            params.failed = true;
         }
     }
-    Ok(true)
 }
 
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<fs::File>>> where P: AsRef<std::path::Path> {
-    let file = fs::File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+/// Move a task to another task list, saving both lists
+/// 
+/// The move isn't recorded in the history, as each list has its own history and undoing it in a single list
+/// would duplicate or lose the task
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration, used to resolve conflicts
+/// * `params` - the task lists
+/// * `task` - the task to move, from the shown list
+/// * `other` - the index of the list among the other lists
+fn move_task(rofi_config : &RofiParams, params : &mut Params, task : Rc<Task>, other : usize) {
+    let task = params.list.todos.remove(task).expect("Some references to task were not deleted");
+    save(rofi_config, params);
+    params.switch(other);
+    add_task(&mut params.list.todos, task);
+    save(rofi_config, params);
+    params.switch(other);
 }
 
-fn save_config(config_file: &std::path::PathBuf, todos: &mut Indexer<Task>) -> Result<bool,String> {
-    let mut save = String::new();
-    for todo in todos.get_main_index() {
-        save.push_str(&todo.to_todotxt());
-        save.push_str("\n");
+/// Modify a task and save it immediately
+/// 
+/// Returns the updated task
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration, used to resolve conflicts
+/// * `params` - the tasks and their file
+/// * `task` - the task to modify
+/// * `modify` - the modification to apply to the task
+fn update_task(rofi_config : &RofiParams, params : &mut Params, task : Rc<Task>, modify : impl FnOnce(&mut Task)) -> Rc<Task> {
+    transaction(rofi_config, params, |todos| {
+        let mut t = todos.remove(task).expect("Some references to task were not deleted");
+        modify(&mut t);
+        add_task(todos, t)
+    })
+}
+
+/// Move the completed tasks to the archive file
+/// 
+/// The tasks are appended to the archive in the order of the task list file,
+/// before being removed from the task list, so none is lost if something fails.
+/// Returns the number of archived tasks
+/// 
+/// Arguments:
+/// 
+/// * `rofi_config` - the Rofi configuration, used to resolve conflicts
+/// * `params` - the tasks and their files
+fn archive_done(rofi_config : &RofiParams, params : &mut Params) -> Result<usize, String> {
+    let done = params.list.todos.index(&String::from("done")).unwrap().into_iter().collect::<Vec<Rc<Task>>>();
+    if done.is_empty() {
+        return Ok(0);
+    }
+    // The tasks not saved yet come last
+    let in_file_order = |file : &TodoFile, mut tasks : Vec<Rc<Task>>| {
+        tasks.sort_by_key(|t| file.line_number(t.get_id()).unwrap_or(usize::MAX));
+        tasks
+    };
+    if params.list.merged.is_empty() {
+        append_tasks(&params.list.done_file, in_file_order(&params.list.file, done.clone()).iter().map(|t| t.as_ref()))?;
+    }
+    // In the combined view, the tasks are archived next to the file of their list
+    for (origin, merged) in params.list.merged.iter().enumerate() {
+        let tasks = done.iter().filter(|t| params.list.origin(t) == origin).cloned().collect::<Vec<_>>();
+        if !tasks.is_empty() {
+            append_tasks(&merged.done_file, in_file_order(&merged.file, tasks).iter().map(|t| t.as_ref()))?;
+        }
     }
+    let count = done.len();
+    // Not recorded in the history: undoing it would leave the tasks in the archive too
+    for task in done {
+        params.list.todos.remove(task);
+    }
+    save_list(rofi_config, &mut params.list)?;
+    Ok(count)
+}
 
-    match fs::write(config_file, save) {
-        Ok(_) => Ok(true),
-        Err(e) => Err(e.to_string())
+/// Ask the user how to resolve a conflict between the tasks and the file modified outside RofiTodo
+fn conflict_selector(rofi_config : &RofiParams, conflict : &Conflict) -> Resolution {
+    let show = |lines : &Vec<String>| if lines.is_empty() { String::from(rofi_config.tr("conflict.removed")) } else { lines.join("\n") };
+    let msg = format!("{}\n\n{} :\n{}\n\n{} :\n{}\n\n{} :\n{}", rofi_config.tr("conflict.message"),
+                      bold(rofi_config.tr("conflict.before")), show(&conflict.base),
+                      bold("RofiTodo"), show(&conflict.ours),
+                      bold(rofi_config.tr("conflict.file")), show(&conflict.theirs));
+    let menu = vec!["conflict.ours", "conflict.theirs", "conflict.both"];
+    let selection = match menu::new(rofi_config).msg(msg).prompt(rofi_config.tr("prompt.conflict")).select_range(0,menu.len()-1).run(translate(rofi_config, &menu)) {
+        Ok(selection) => selection,
+        Err(e) => {
+            eprintln!("{}", e);
+            Selection::default()
+        }
+    };
+    match selection.item(0, menu) {
+        Some("conflict.ours") => Resolution::Ours,
+        Some("conflict.theirs") => Resolution::Theirs,
+        // Keeping both versions when the user quits or the menu fails, so nothing is lost
+        _ => Resolution::Both
     }
 }
 
+/// Mark a task as done, creating its next occurrence if it is a recurring task
+/// 
+/// Returns the completed task
+/// 
+/// Arguments:
+/// 
+/// * `todos` - the tasks
+/// * `task` - the task to complete
+fn complete_task(todos : &mut Indexer<Task>, task : Rc<Task>) -> Rc<Task> {
+    let mut t = todos.remove(task).expect("Some references to task were not deleted");
+    t.set_completed();
+    // Spawning the next occurrence of a recurring task
+    if let Some(next) = t.next_occurrence(Local::now().date_naive()) {
+        add_task(todos,next);
+    }
+    add_task(todos,t)
+}
+
 fn add_task(idx: &mut Indexer<Task>, tsk: Task) -> Rc<Task> {
     if !tsk.completion {
+        // Like the content index, the tag indexes leave out the upcoming tasks
+        let today = Local::now().date_naive();
         for tag in tsk.get_context_tags().clone() {
             let mut idx_name = String::from("context_");
             idx_name.push_str(&tag);
-            idx.new_autoremove_index(idx_name, move |x|!x.completion && x.get_context_tags().contains(&tag), Task::comp_content);
+            idx.new_autoremove_index(idx_name, move |x|!x.completion && !x.is_upcoming(today) && x.get_context_tags().contains(&tag), Task::comp_content);
         }
         for tag in tsk.get_project_tags().clone() {
             let mut idx_name = String::from("project_");
             idx_name.push_str(&tag);
-            idx.new_autoremove_index(idx_name, move |x|!x.completion && x.get_project_tags().contains(&tag), Task::comp_content);
+            idx.new_autoremove_index(idx_name, move |x|!x.completion && !x.is_upcoming(today) && x.get_project_tags().contains(&tag), Task::comp_content);
         }
     }
     idx.add(tsk)
 }
 
 
+/// A task list file, with its tasks
+struct TaskList {
+    /// The name of the list, shown in the list menu
+    name : String,
+    /// The position of the list in the list menu
+    position : usize,
+    todos : Indexer<Task>,
+    file : TodoFile,
+    done_file : std::path::PathBuf,
+    history : History,
+    /// The lists shown together in the combined view, by position, their tasks being moved to this list
+    merged : Vec<TaskList>,
+    /// The index in `merged` of the list of each task, by identifier of the task
+    origins : HashMap<usize, usize>,
+    /// The index in `merged` of the list of the saved tasks, by todo.txt line, to find the list of a task restored by undo
+    saved : HashMap<String, usize>,
+    /// The lists changed by each operation of the combined view which can be undone, the most recent last,
+    /// the operations being recorded in the history of each list
+    undo_lists : Vec<BTreeSet<usize>>,
+    /// The lists changed by each undone operation of the combined view which can be redone, the most recently undone last
+    redo_lists : Vec<BTreeSet<usize>>
+}
+
+impl TaskList {
+    fn new(name : String, todos : Indexer<Task>, file : TodoFile, done_file : std::path::PathBuf, history : History) -> Self {
+        TaskList { name, position : 0, todos, file, done_file, history, merged : Vec::new(), origins : HashMap::new(), saved : HashMap::new(),
+                   undo_lists : Vec::new(), redo_lists : Vec::new() }
+    }
+
+    /// Get the index in `merged` of the list of a task of the combined view
+    /// 
+    /// A task which was never saved belongs to the main list, the first one
+    /// 
+    /// Arguments:
+    /// 
+    /// * `task` - the task
+    fn origin(&self, task : &Task) -> usize {
+        self.origin_of(task.get_id(), &task.to_todotxt())
+    }
+
+    /// Get the index in `merged` of the list of a task of the combined view, given its identifier and its todo.txt line
+    /// 
+    /// Arguments:
+    /// 
+    /// * `id` - the identifier of the task
+    /// * `line` - the todo.txt line of the task
+    fn origin_of(&self, id : usize, line : &str) -> usize {
+        match self.origins.get(&id) {
+            Some(origin) => *origin,
+            None => self.saved.get(line).copied().unwrap_or(0)
+        }
+    }
+
+    /// Record an operation in the history
+    /// 
+    /// In the combined view, the changes made to the tasks of each list are recorded in the history of the list,
+    /// so they can still be undone once the combined view is left
+    /// 
+    /// Arguments:
+    /// 
+    /// * `before` - the tasks before the operation
+    fn record(&mut self, before : Snapshot) -> Result<(), String> {
+        if self.merged.is_empty() {
+            return self.history.record(before, self.todos.get_main_index()).map(|_| ());
+        }
+        self.inherit_origins(&before);
+        let mut changed = BTreeSet::new();
+        let mut errors = Vec::new();
+        for origin in 0..self.merged.len() {
+            let before = before.iter().filter(|(id, line)| self.origin_of(**id, line) == origin).map(|(id, line)| (*id, line.clone())).collect();
+            let tasks = self.todos.get_main_index().iter().filter(|t| self.origin(t) == origin).collect::<Vec<_>>();
+            // A failed journal save still records the operation
+            match self.merged[origin].history.record(before, tasks) {
+                Ok(false) => (),
+                Ok(true) => { changed.insert(origin); },
+                Err(s) => {
+                    changed.insert(origin);
+                    errors.push(s);
+                }
+            }
+        }
+        if !changed.is_empty() {
+            // The operations undone in the combined view can't be redone anymore, in any list
+            for origin in std::mem::take(&mut self.redo_lists).into_iter().flatten() {
+                if let Err(s) = self.merged[origin].history.clear_redo() {
+                    errors.push(s);
+                }
+            }
+            self.undo_lists.push(changed);
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+    }
+
+    /// Is there an operation to undo
+    fn can_undo(&self) -> bool {
+        if self.merged.is_empty() { self.history.can_undo() } else { !self.undo_lists.is_empty() }
+    }
+
+    /// Is there an operation to redo
+    fn can_redo(&self) -> bool {
+        if self.merged.is_empty() { self.history.can_redo() } else { !self.redo_lists.is_empty() }
+    }
+
+    /// Undo the last operation
+    /// 
+    /// In the combined view, the operation is undone in the history of every list it changed
+    fn undo(&mut self) -> Result<(), String> {
+        if self.merged.is_empty() {
+            return self.history.undo(&mut self.todos, add_task);
+        }
+        let lists = match self.undo_lists.pop() {
+            Some(lists) => lists,
+            None => return Ok(())
+        };
+        let (applied, result) = self.replay(lists, true);
+        if !applied.is_empty() {
+            self.redo_lists.push(applied);
+        }
+        result
+    }
+
+    /// Redo the last undone operation
+    /// 
+    /// In the combined view, the operation is redone in the history of every list it changed
+    fn redo(&mut self) -> Result<(), String> {
+        if self.merged.is_empty() {
+            return self.history.redo(&mut self.todos, add_task);
+        }
+        let lists = match self.redo_lists.pop() {
+            Some(lists) => lists,
+            None => return Ok(())
+        };
+        let (applied, result) = self.replay(lists, false);
+        if !applied.is_empty() {
+            self.undo_lists.push(applied);
+        }
+        result
+    }
+
+    /// Undo or redo the last operation of the histories of some lists of the combined view
+    /// 
+    /// Returns the lists whose operation was applied, and the errors of the other ones
+    /// 
+    /// Arguments:
+    /// 
+    /// * `lists` - the indexes in `merged` of the lists
+    /// * `undo` - undo the operations instead of redoing them
+    fn replay(&mut self, lists : BTreeSet<usize>, undo : bool) -> (BTreeSet<usize>, Result<(), String>) {
+        let TaskList { todos, merged, origins, .. } = self;
+        let mut applied = BTreeSet::new();
+        let mut errors = Vec::new();
+        for origin in lists {
+            // The tasks restored by the operation belong to the list
+            let add = |todos : &mut Indexer<Task>, task : Task| {
+                let task = add_task(todos, task);
+                origins.insert(task.get_id(), origin);
+                task
+            };
+            let history = &mut merged[origin].history;
+            match if undo { history.undo(todos, add) } else { history.redo(todos, add) } {
+                Ok(_) => { applied.insert(origin); },
+                Err(s) => errors.push(s)
+            }
+        }
+        (applied, if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) })
+    }
+
+    /// Give the tasks added by an operation of the combined view the list of the tasks it changed
+    /// 
+    /// The next occurrence of a completed recurring task is saved to the list of the task, not to the main list
+    /// 
+    /// Arguments:
+    /// 
+    /// * `before` - the tasks before the operation
+    fn inherit_origins(&mut self, before : &Snapshot) {
+        let tasks = self.todos.get_main_index();
+        let changed = tasks.iter().filter(|t| before.get(&t.get_id()).is_some_and(|line| *line != t.to_todotxt())).map(|t| self.origin(t)).collect::<BTreeSet<_>>();
+        // The added tasks can't be given a list when the changed tasks come from several lists
+        if changed.len() != 1 {
+            return;
+        }
+        let origin = changed.into_iter().next().unwrap();
+        let added = tasks.iter().filter(|t| !before.contains_key(&t.get_id())).map(|t| t.get_id()).collect::<Vec<_>>();
+        for id in added {
+            self.origins.insert(id, origin);
+        }
+    }
+
+    /// Load a task list file and its journal
+    /// 
+    /// Arguments:
+    /// 
+    /// * `name` - the name of the list
+    /// * `path` - the path of the task list file
+    /// * `done_file` - the path of the archive of the completed tasks
+    /// * `backups` - the number of backups of the file to keep
+    /// * `journal` - load the history of the modifications from the journal of the file
+    fn load(name : String, path : &std::path::Path, done_file : std::path::PathBuf, backups : usize, journal : bool) -> Result<Self, String> {
+        let mut todos = new_indexer();
+        let mut file = load_config(path, &mut todos)?;
+        file.set_backups(backups);
+        let history = if journal {
+            let (history, warnings) = History::load(history::journal_path(path))?;
+            for warning in warnings {
+                eprintln!("{}", warning);
+            }
+            history
+        } else {
+            History::new()
+        };
+        Ok(TaskList::new(name, todos, file, done_file, history))
+    }
+}
+
 struct Params {
     sort : SortTaskBy,
-    todos : Indexer<Task>,
+    /// The task list shown in the menus
+    list : TaskList,
+    /// The other task lists
+    others : Vec<TaskList>,
+    /// The keys of the quick actions of the task list
+    keys : Keybindings,
+    /// Did a save fail during the session
+    failed : bool,
 }
 
 impl Params {
-    fn new(sort : SortTaskBy, idx : Indexer<Task>) -> Self {
-        Params { sort : sort, todos : idx }
+    fn new(sort : SortTaskBy, list : TaskList) -> Self {
+        Params { sort, list, others : Vec::new(), keys : Keybindings::default(), failed : false }
+    }
+
+    /// Add a task list, after the other ones in the list menu
+    /// 
+    /// Arguments:
+    /// 
+    /// * `list` - the task list
+    fn add_list(&mut self, mut list : TaskList) -> Result<(), String> {
+        if self.list.name == list.name || self.others.iter().any(|l| l.name == list.name) {
+            return Err(format!("duplicated list: {}", list.name));
+        }
+        list.position = self.others.len() + 1;
+        self.others.push(list);
+        Ok(())
+    }
+
+    /// Show another task list in the menus, the shown list taking its place among the other lists
+    /// 
+    /// Switching twice to the same other list shows the first list again
+    /// 
+    /// Arguments:
+    /// 
+    /// * `other` - the index of the list among the other lists
+    fn switch(&mut self, other : usize) {
+        std::mem::swap(&mut self.list, &mut self.others[other]);
+    }
+
+    /// Are there several task lists
+    fn has_lists(&self) -> bool {
+        !self.others.is_empty() || !self.list.merged.is_empty()
+    }
+
+    /// Get the task lists ordered by position, with their number of tasks to do
+    fn lists(&self) -> Vec<(&TaskList, usize)> {
+        let mut lists = if self.list.merged.is_empty() {
+            std::iter::once(&self.list).chain(&self.others).map(|list| (list, open_tasks(list.todos.get_main_index()))).collect::<Vec<_>>()
+        } else {
+            let mut open = vec![0; self.list.merged.len()];
+            for task in self.list.todos.get_main_index().iter().filter(|t| !t.completion) {
+                open[self.list.origin(task)] += 1;
+            }
+            self.list.merged.iter().zip(open).collect()
+        };
+        lists.sort_by_key(|(list, _)| list.position);
+        lists
+    }
+
+    /// Show the task list at a position of the list menu, leaving the combined view
+    /// 
+    /// Arguments:
+    /// 
+    /// * `position` - the position of the list
+    fn show(&mut self, position : usize) {
+        self.split();
+        if let Some(other) = self.others.iter().position(|list| list.position == position) {
+            self.switch(other);
+        }
+    }
+
+    /// Show the tasks of every list together, sorted together, in the combined view
+    /// 
+    /// The tasks are moved to a single list, which remembers the list of each task to save it to the right file.
+    /// The operations of the combined view are recorded in the history of the lists they change
+    /// 
+    /// Arguments:
+    /// 
+    /// * `name` - the name of the combined view
+    fn combine(&mut self, name : String) {
+        if self.others.is_empty() {
+            return;
+        }
+        // The combined view has no file of its own, its tasks being saved to the files of the merged lists
+        let mut lists = std::mem::take(&mut self.others);
+        lists.push(std::mem::replace(&mut self.list, TaskList::new(name, new_indexer(), TodoFile::new(std::path::PathBuf::new()), std::path::PathBuf::new(), History::new())));
+        lists.sort_by_key(|list| list.position);
+        for (origin, list) in lists.iter_mut().enumerate() {
+            for task in take_tasks(&mut list.todos) {
+                self.list.origins.insert(task.get_id(), origin);
+                add_task(&mut self.list.todos, task);
+            }
+        }
+        self.list.merged = lists;
+    }
+
+    /// Leave the combined view, the main list being shown
+    /// 
+    /// The tasks are moved back to their list, whose history holds the operations of the combined view
+    fn split(&mut self) {
+        if self.list.merged.is_empty() {
+            return;
+        }
+        let mut lists = std::mem::take(&mut self.list.merged);
+        for task in take_tasks(&mut self.list.todos) {
+            add_task(&mut lists[self.list.origin(&task)].todos, task);
+        }
+        self.list = lists.remove(0);
+        self.others = lists;
     }
 
     fn get_sort_string(&self) -> String {
@@ -361,43 +1243,686 @@ impl Params {
     }
 }
 
-fn main() {
-    let mut todos = Indexer::<Task>::new();
-
-    let args = Cli::from_args();
+/// Get the value of a flag which can be enabled or disabled on the command line, or else in the configuration file
+/// 
+/// Arguments:
+/// 
+/// * `enabled` - the option enabling the flag was given
+/// * `disabled` - the option disabling the flag was given
+/// * `setting` - the value of the configuration file, if any
+fn flag(enabled : bool, disabled : bool, setting : Option<bool>) -> bool {
+    match (enabled, disabled) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => setting.unwrap_or(false)
+    }
+}
 
-    let sort  = match args.sort.as_ref() {
+/// Get the sort order named by the `--sort` option
+/// 
+/// Arguments:
+/// 
+/// * `sort` - the name of the sort order
+fn parse_sort(sort : &str) -> SortTaskBy {
+    match sort.to_lowercase().as_ref() {
         "content"   => SortTaskBy::Content,
         "creation"  => SortTaskBy::CreationDate,
         "priority"  => SortTaskBy::Priority,
         "due"       => SortTaskBy::DueDate,
         _           => SortTaskBy::Content
-    };
+    }
+}
 
-    todos.new_index(String::from("content"),    |x|!x.completion, Task::comp_content);
-    todos.new_index(String::from("creation"),   |x|!x.completion, Task::comp_creation_date);
-    todos.new_index(String::from("priority"),   |x|!x.completion, Task::comp_priority);
-    todos.new_index(String::from("due"),        |x|!x.completion, Task::comp_due_date);
+/// Remove all the tasks from an indexer, keeping their identity
+/// 
+/// Arguments:
+/// 
+/// * `todos` - the tasks
+fn take_tasks(todos : &mut Indexer<Task>) -> Vec<Task> {
+    let tasks = todos.get_main_index().iter().cloned().collect::<Vec<_>>();
+    tasks.into_iter().map(|task| todos.remove(task).expect("Some references to task were not deleted")).collect()
+}
+
+/// Create an `Indexer` with the indexes used by the menus
+fn new_indexer() -> Indexer<Task> {
+    let mut todos = Indexer::<Task>::new();
+    // Tasks with a threshold date in the future are only shown in the "upcoming" index
+    let today = Local::now().date_naive();
+    todos.new_index(String::from("content"),    move |x|!x.completion && !x.is_upcoming(today), Task::comp_content);
+    todos.new_index(String::from("creation"),   move |x|!x.completion && !x.is_upcoming(today), Task::comp_creation_date);
+    todos.new_index(String::from("priority"),   move |x|!x.completion && !x.is_upcoming(today), Task::comp_priority);
+    todos.new_index(String::from("due"),        move |x|!x.completion && !x.is_upcoming(today), Task::comp_due_date);
+    todos.new_index(String::from("upcoming"),   move |x|!x.completion && x.is_upcoming(today), Task::comp_threshold_date);
     todos.new_index(String::from("done"),       |x|x.completion, Task::comp_content);
+    todos
+}
+
+fn main() {
+    // The options given on the command line take precedence over the configuration file
+    let settings = match settings::default_path().map(|path| Settings::load(&path)) {
+        Some(Ok(settings)) => settings,
+        Some(Err(s)) => {
+            eprintln!("{}", s);
+            std::process::exit(1);
+        },
+        None => Settings::default()
+    };
+    let (default_file, source) = match &settings.todo_file {
+        Some(file) => (file.clone(), "the configuration file"),
+        None => todo_file::default_path()
+    };
+    let help = format!("The task list file is {} by default, from {}", default_file.display(), source);
+    let args = Cli::from_clap(&Cli::clap().after_help(help.as_str()).get_matches());
+    let config = args.config.clone().unwrap_or(default_file);
+    let backups = args.backups.or(settings.backups).unwrap_or(3);
+    let sort = parse_sort(args.sort.as_deref().or(settings.sort.as_deref()).unwrap_or("content"));
 
-    let rofi_config = RofiParams { no_config : args.no_config, case_insensitive : args.case_insensitive };
-    let config = args.config;
-    match load_config(&config, &mut todos) {
-        Ok(_) => (),
+    let rofi_config = RofiParams {
+        no_config : flag(args.no_config, args.rofi_config, settings.no_config),
+        case_insensitive : flag(args.case_insensitive, args.case_sensitive, settings.case_insensitive),
+        markup : flag(args.markup, args.no_markup, settings.markup),
+        backend : args.backend.or_else(|| settings.backend.as_deref().and_then(|b| b.parse().ok())).unwrap_or(Backend::Rofi),
+        lang : args.lang.or_else(|| settings.lang.as_deref().and_then(|l| l.parse().ok())).unwrap_or_else(Lang::from_env),
+        date_format : settings.date_format.clone().unwrap_or_else(|| String::from("%Y-%m-%d")),
+        theme : settings.theme.clone(),
+        messages : settings.messages().unwrap_or_default()
+    };
+    if let Some(n) = args.restore_backup {
+        match TodoFile::restore_backup(&config, n, backups) {
+            Ok(_) => println!("Backup {} restored to {}", n, config.display()),
+            Err(s) => {
+                eprintln!("{}", s);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let journal = flag(args.journal, args.no_journal, settings.journal);
+    let done_file = args.done_file.clone().or_else(|| settings.done_file.clone()).unwrap_or_else(|| config.with_file_name("done.txt"));
+    // The main list is named after its file, such as "todo"
+    let name = config.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| String::from("todo"));
+    let list = match TaskList::load(name, &config, done_file, backups, journal) {
+        Ok(list) => list,
         Err(s) => {
-            println!("{}", s);
-            return;
+            eprintln!("{}", s);
+            std::process::exit(1);
         }
     };
+    let mut parameters = Params::new(sort, list);
+    parameters.keys = match settings.keybindings(parameters.keys) {
+        Ok(keys) => keys,
+        Err(s) => {
+            eprintln!("{}", s);
+            std::process::exit(1);
+        }
+    };
+    for binding in &args.keys {
+        if let Err(s) = parameters.keys.set(binding) {
+            eprintln!("{}", s);
+            std::process::exit(1);
+        }
+    }
 
-    let mut parameters = Params::new(sort, todos);
+    if args.archive {
+        match archive_done(&rofi_config, &mut parameters) {
+            Ok(n) => println!("{} tasks archived to {}", n, parameters.list.done_file.display()),
+            Err(s) => {
+                eprintln!("{}", s);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(command) = args.command {
+        if let Err(s) = commands::run(command, &mut parameters) {
+            eprintln!("{}", s);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // The other lists are only used by the menus, the commands and the archive work on the main list
+    let mut lists = settings.lists.clone();
+    for list in &args.lists {
+        match list.split_once('=') {
+            // A list of the configuration file is replaced by the option with the same name
+            Some((name, path)) if !name.trim().is_empty() => {
+                let path = settings::expand_home(std::path::PathBuf::from(path.trim()));
+                match lists.iter_mut().find(|(n, _)| n == name.trim()) {
+                    Some(list) => list.1 = path,
+                    None => lists.push((String::from(name.trim()), path))
+                }
+            },
+            _ => {
+                eprintln!("malformed list: {} (expected name=path)", list);
+                std::process::exit(1);
+            }
+        };
+    }
+    for (name, path) in lists {
+        let list = TaskList::load(name, &path, todo_file::list_done_path(&path), backups, journal);
+        if let Err(s) = list.and_then(|list| parameters.add_list(list)) {
+            eprintln!("{}", s);
+            std::process::exit(1);
+        }
+    }
 
     loop {
-        if show_main_menu(&rofi_config, &mut parameters) == MenuStatus::EXIT { break }
+        match show_main_menu(&rofi_config, &mut parameters) {
+            Ok(MenuStatus::Exit) => break,
+            Ok(_) => (),
+            Err(e) => {
+                // Saving the tasks before exiting, in case a save failed
+                save_lists(&rofi_config, &mut parameters);
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
     }
 
-    match save_config(&config, &mut parameters.todos) {
-        Ok(_) => (),
-        Err(s) => println!("{}", s)
-    };
+    // Every modification is already saved, unless a save failed
+    save_lists(&rofi_config, &mut parameters);
+    if parameters.failed {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn upcoming_tasks_in_tag_lists() {
+        let mut todos = new_indexer();
+        add_task(&mut todos, Task::from_todotxt(String::from("call mom @phone")).unwrap());
+        add_task(&mut todos, Task::from_todotxt(String::from("call dad @phone t:2999-01-01")).unwrap());
+        let phone = todos.index(&String::from("context_phone")).unwrap().into_iter().map(|task| task.get_content().to_string()).collect::<Vec<_>>();
+        assert_eq!(phone, vec!["call mom @phone"]);
+        assert_eq!(todos.index(&String::from("upcoming")).unwrap().into_iter().count(), 1);
+    }
+
+    #[test]
+    fn duplicated_tasks_round_trip() {
+        let dir = TestDir::new("duplicates");
+        let file = dir.join("todo.txt");
+        std::fs::write(&file, "call mom @phone\ncall mom @phone\nx call mom @phone\n").unwrap();
+
+        let mut todos = new_indexer();
+        let mut todo_file = load_config(&file, &mut todos).unwrap();
+        assert_eq!(todos.get_main_index().len(), 3);
+        assert_eq!(todos.index(&String::from("content")).unwrap().into_iter().count(), 2);
+        assert_eq!(todos.index(&String::from("done")).unwrap().into_iter().count(), 1);
+        assert_eq!(todos.index(&String::from("context_phone")).unwrap().into_iter().count(), 2);
+
+        // Removing one of the duplicates keeps the other one
+        let task = todos.index(&String::from("content")).unwrap().into_iter().next().unwrap();
+        let removed = todos.remove(task).unwrap();
+        assert_eq!(removed.get_content(), "call mom @phone");
+        assert_eq!(todos.index(&String::from("content")).unwrap().into_iter().count(), 1);
+        add_task(&mut todos, removed);
+
+        save_config(&RofiParams { no_config : true, ..RofiParams::default() }, &mut todo_file, &mut todos).unwrap();
+        let mut reloaded = new_indexer();
+        load_config(&file, &mut reloaded).unwrap();
+        assert_eq!(reloaded.get_main_index().len(), 3);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "call mom @phone\ncall mom @phone\nx call mom @phone\n");
+    }
+
+    #[test]
+    fn modifications_are_saved_immediately() {
+        let dir = TestDir::new("transaction");
+        let file = dir.join("todo.txt");
+        std::fs::write(&file, "# groceries\ncall mom\n").unwrap();
+        let rofi_config = RofiParams { no_config : true, ..RofiParams::default() };
+
+        let mut todos = new_indexer();
+        let todo_file = load_config(&file, &mut todos).unwrap();
+        let mut params = Params::new(SortTaskBy::Content, TaskList::new(String::from("todo"), todos, todo_file, dir.join("done.txt"), History::new()));
+
+        transaction(&rofi_config, &mut params, |todos| add_task(todos, Task::from_todotxt(String::from("buy milk")).unwrap()));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "# groceries\ncall mom\nbuy milk\n");
+
+        let task = params.list.todos.index(&String::from("content")).unwrap().into_iter().find(|t| t.get_content() == "call mom").unwrap();
+        let task = update_task(&rofi_config, &mut params, task, |t| t.priority = Some('A'));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "# groceries\n(A) call mom\nbuy milk\n");
+
+        transaction(&rofi_config, &mut params, |todos| { todos.remove(task); });
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "# groceries\nbuy milk\n");
+    }
+
+    #[test]
+    fn archive_done_tasks() {
+        let dir = TestDir::new("archive_session");
+        let file = dir.join("todo.txt");
+        let done_file = dir.join("done.txt");
+        std::fs::write(&file, "x 2021-10-02 pay rent\ncall mom\nx buy milk\n").unwrap();
+        std::fs::write(&done_file, "x 2021-09-02 pay rent\n").unwrap();
+        let rofi_config = RofiParams { no_config : true, ..RofiParams::default() };
+
+        let mut todos = new_indexer();
+        let todo_file = load_config(&file, &mut todos).unwrap();
+        let mut params = Params::new(SortTaskBy::Content, TaskList::new(String::from("todo"), todos, todo_file, done_file.clone(), History::new()));
+
+        assert_eq!(archive_done(&rofi_config, &mut params).unwrap(), 2);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "call mom\n");
+        // The tasks are archived in the order of the file
+        assert_eq!(std::fs::read_to_string(&done_file).unwrap(), "x 2021-09-02 pay rent\nx 2021-10-02 pay rent\nx buy milk\n");
+        assert_eq!(params.list.todos.index(&String::from("done")).unwrap().into_iter().count(), 0);
+        assert_eq!(archive_done(&rofi_config, &mut params).unwrap(), 0);
+
+        // A failed save of the task list is reported, the tasks being in the archive anyway
+        std::fs::remove_file(&file).unwrap();
+        std::fs::create_dir(&file).unwrap();
+        transaction(&rofi_config, &mut params, |todos| add_task(todos, Task::from_todotxt(String::from("x call dad")).unwrap()));
+        assert!(archive_done(&rofi_config, &mut params).is_err());
+        assert!(std::fs::read_to_string(&done_file).unwrap().ends_with("x buy milk\nx call dad\n"));
+    }
+}
+
+#[cfg(test)]
+mod ui_tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    const MAIN_MENU : [&str; 6] = ["+ add", "~ done", "~ upcoming", "@ project tags", "@ context tags", "🔍 search"];
+
+    /// Start a session on a task list file with the mock backend
+    fn session(name : &str, content : &str) -> (TestDir, Params) {
+        let dir = TestDir::new(&format!("ui_{}", name));
+        let file = dir.join("todo.txt");
+        std::fs::write(&file, content).unwrap();
+        let mut todos = new_indexer();
+        let todo_file = load_config(&file, &mut todos).unwrap();
+        let params = Params::new(SortTaskBy::Content, TaskList::new(String::from("todo"), todos, todo_file, dir.join("done.txt"), History::new()));
+        (dir, params)
+    }
+
+    fn rofi_config() -> RofiParams {
+        RofiParams { backend : Backend::Mock, ..RofiParams::default() }
+    }
+
+    #[test]
+    fn add_with_date_and_complete() {
+        let (dir, mut params) = session("complete", "");
+        let today = Local::now().date_naive();
+        let month = today.format("%Y-%m").to_string();
+        let task = format!("{}-28 : buy milk", month);
+        mock::script(&["+ add", "buy milk", "+ add date", "~ pick a date", "28", &task, "✔ mark as done", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), format!("x {} {} buy milk due:{}-28\n", today, today, month));
+
+        let shown = mock::shown();
+        assert_eq!(shown.len(), 8);
+        assert_eq!(shown[0].entries, [&MAIN_MENU[..], &["* exit"]].concat());
+        assert_eq!(shown[0].active, Some((0, 6)));
+        assert_eq!(shown[1].prompt.as_deref(), Some("Task"));
+        assert!(shown[1].text_only);
+        assert_eq!(shown[2].entries, vec!["✔ validate", "+ add date", "* cancel"]);
+        assert_eq!(shown[3].prompt.as_deref(), Some("Date"));
+        // The calendar of the current month, today being highlighted and selected
+        assert_eq!(shown[4].msg, Some(today.format("%B %Y").to_string()));
+        assert_eq!(shown[4].grid, Some((7, shown[4].entries.len() / 7)));
+        assert_eq!(shown[4].entries[0], "Mo");
+        let today_row = shown[4].entries.iter().position(|e| *e == today.format("%-d").to_string()).unwrap();
+        assert_eq!(shown[4].highlighted, vec![(today_row, Highlight::Active)]);
+        assert_eq!(shown[4].selected, Some(today_row as u32));
+        // The new task is in the main list, and its menu shows its details
+        assert_eq!(shown[5].entries.last(), Some(&task));
+        assert_eq!(shown[6].prompt.as_deref(), Some("Edit"));
+        assert!(shown[6].msg.as_ref().unwrap().contains("buy milk"));
+        assert_eq!(shown[7].entries, [&MAIN_MENU[..], &["↶ undo", "* exit"]].concat());
+    }
+
+    #[test]
+    fn typed_dates() {
+        let (dir, mut params) = session("dates", "call mom\n");
+        let today = Local::now().date_naive();
+        let tomorrow = today.succ_opt().unwrap();
+        // A date changed after seeing it, then a due date typed in the task
+        mock::script(&["call mom", "+ change date", "tomorrow", "← change", "+3d", "✔ validate", "* cancel",
+            "+ add", "pay rent due:tomorrow", "✔ validate", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        let in_3_days = today + chrono::Duration::days(3);
+        let content = std::fs::read_to_string(dir.join("todo.txt")).unwrap();
+        assert_eq!(content, format!("call mom due:{}\n{} pay rent due:{}\n", in_3_days, today, tomorrow));
+
+        let shown = mock::shown();
+        assert_eq!(shown[2].entries, vec!["~ pick a date"]);
+        assert_eq!(shown[3].msg, Some(format!("tomorrow : {}", tomorrow.format("%A %Y-%m-%d"))));
+        // The typed date is kept when changing it
+        assert_eq!(shown[4].pretext.as_deref(), Some("tomorrow"));
+    }
+
+    #[test]
+    fn calendar() {
+        let (_dir, params) = session("calendar", "pay rent due:2026-11-02\ncall mom due:2026-11-02\n");
+        let first = chrono::NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        // Showing the next month, then picking a day
+        mock::script(&["~ pick a date", &mock::entry(48), "5"]);
+
+        let date = date_selector(&rofi_config(), &params.list.todos, first);
+        assert_eq!(date, Ok(chrono::NaiveDate::from_ymd_opt(2026, 11, 5)));
+        assert!(mock::finished());
+
+        let shown = mock::shown();
+        assert_eq!(shown[1].msg.as_deref(), Some("October 2026"));
+        assert_eq!(shown[2].msg.as_deref(), Some("November 2026"));
+        // November 2026 starts on a Sunday, two tasks are due on the 2nd
+        assert_eq!(shown[2].entries[7..15], ["", "", "", "", "", "", "1", "2 (2)"]);
+    }
+
+    #[test]
+    fn change_priority_remove_and_undo() {
+        let (dir, mut params) = session("undo", "call mom\n");
+        mock::script(&["call mom", "+ change priority", "A", "* cancel", "(A) call mom", "! remove", "↶ undo", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), "(A) call mom\n");
+
+        let shown = mock::shown();
+        // The task menu is shown again with the new priority
+        assert!(shown[3].msg.as_ref().unwrap().contains("A"));
+        assert_eq!(shown[4].entries.last().unwrap(), "(A) call mom");
+        // The removed task is not listed anymore, until the removal is undone
+        assert_eq!(shown[6].entries, [&MAIN_MENU[..], &["↶ undo", "* exit"]].concat());
+        assert_eq!(shown[6].active, Some((0, 7)));
+        assert_eq!(shown[7].entries.last().unwrap(), "(A) call mom");
+        assert!(shown[7].entries.contains(&String::from("↷ redo")));
+    }
+
+    #[test]
+    fn select_tag() {
+        let (_dir, mut params) = session("tags", "call mom +Home\npay rent +Work\nbuy milk +Home\n");
+        // A typed text is not a tag, the tags being found by their entry
+        mock::script(&["@ project tags", "+Home", &mock::entry(2), ""]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        let shown = mock::shown();
+        assert_eq!(shown[1].entries, ["← back", "Home", "Work"]);
+        assert_eq!(shown[2].entries, shown[1].entries);
+        assert_eq!(shown[3].entries, ["← back", "pay rent +Work"]);
+    }
+
+    #[test]
+    fn failed_undo() {
+        let (_dir, mut params) = session("failed_undo", "call mom\n");
+        let task = transaction(&rofi_config(), &mut params, |todos| add_task(todos, Task::from_todotxt(String::from("buy milk")).unwrap()));
+        // Removing the task outside the history, so the addition can't be undone
+        params.list.todos.remove(task);
+        mock::script(&["↶ undo", "not an entry", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        let shown = mock::shown();
+        // The failure is shown above the keys of the quick actions, once
+        assert!(shown[1].msg.as_ref().unwrap().starts_with("The task was modified since: buy milk\nAlt+d : mark as done"));
+        assert_eq!(shown[0].msg, shown[2].msg);
+    }
+
+    #[test]
+    fn select_duplicated_tasks() {
+        let (dir, mut params) = session("duplicates", "call mom\n* exit\ncall mom\n");
+        // Completing the second "call mom", then the task looking like the exit entry, listed after the undo entry
+        mock::script(&[&mock::entry(9), "✔ mark as done", &mock::entry(8), "✔ mark as done", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        let content = std::fs::read_to_string(dir.join("todo.txt")).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().filter(|l| l.starts_with("x ")).count() == 2);
+        assert!(lines.iter().any(|l| l.starts_with("x ") && l.ends_with(" * exit")));
+        assert!(lines.contains(&"call mom"));
+    }
+
+    #[test]
+    fn quick_actions() {
+        let (dir, mut params) = session("keys", "call mom\n");
+        // Changing the priority, ignoring a key on an entry which isn't a task, then completing the task listed after the undo entry
+        mock::script(&[&mock::key(2, 7), &mock::key(3, 0), &mock::key(1, 8), "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        let content = std::fs::read_to_string(dir.join("todo.txt")).unwrap();
+        let today = Local::now().date_naive();
+        assert_eq!(content, format!("x (A) {} {} call mom\n", today, today));
+
+        let shown = mock::shown();
+        assert_eq!(shown[0].keys, vec![(1, String::from("Alt+d")), (2, String::from("Alt+p")), (3, String::from("Alt+r")), (4, String::from("Alt+e"))]);
+        assert!(shown[0].msg.as_ref().unwrap().contains("Alt+p : change priority"));
+        assert_eq!(shown[1].entries.last().unwrap(), "(A) call mom");
+        // The task stays highlighted after a quick action
+        assert_eq!(shown[1].selected, Some(7));
+        assert_eq!(shown[2].selected, Some(0));
+        assert_eq!(shown[3].entries.len(), 8);
+    }
+
+    #[test]
+    fn markup() {
+        let (dir, mut params) = session("markup", "(A) R&D <meeting> +Work\n");
+        let rofi_config = RofiParams { markup : true, ..rofi_config() };
+        mock::script(&[&mock::entry(7), "* cancel", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config, &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+
+        let shown = mock::shown();
+        assert!(shown[0].markup);
+        assert_eq!(shown[0].entries[7], "<span weight=\"bold\" foreground=\"#d33682\">(A)</span> R&amp;D &lt;meeting&gt; <span style=\"italic\" foreground=\"#268bd2\">+Work</span>");
+        assert!(shown[1].markup);
+        assert!(shown[1].msg.as_ref().unwrap().starts_with("<b>Task</b> : R&amp;D &lt;meeting&gt;"));
+        // The text of the task is unchanged
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), "(A) R&D <meeting> +Work\n");
+    }
+
+    #[test]
+    fn highlighted_tasks() {
+        let (_dir, mut params) = session("highlight", "(A) call mom\npay rent due:2000-01-01\n(A) renew passport due:2000-01-02\nbuy milk\n");
+        mock::script(&["* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        let shown = mock::shown();
+        assert_eq!(&shown[0].entries[7..], ["buy milk", "(A) call mom", "2000-01-01 : pay rent", "(A) 2000-01-02 : renew passport"]);
+        assert_eq!(shown[0].highlighted, vec![(9, Highlight::Urgent), (10, Highlight::Urgent), (8, Highlight::Active), (10, Highlight::Active)]);
+    }
+
+    #[test]
+    fn failing_menu() {
+        let (dir, mut params) = session("failing", "call mom\n");
+        mock::script(&["call mom", "✔ mark as done", &mock::killed()]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Err(RofiError::Killed));
+        assert!(mock::finished());
+        // The modifications made before the failure are saved
+        assert!(std::fs::read_to_string(dir.join("todo.txt")).unwrap().starts_with("x "));
+    }
+
+    #[test]
+    fn failed_save() {
+        let (dir, mut params) = session("failed_save", "call mom\n");
+        // A directory can't be read or replaced by the task list file
+        std::fs::remove_file(dir.join("todo.txt")).unwrap();
+        std::fs::create_dir(dir.join("todo.txt")).unwrap();
+        mock::script(&["call mom", "✔ mark as done", ""]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        assert!(params.failed);
+        assert_eq!(params.list.todos.index(&String::from("done")).unwrap().into_iter().count(), 1);
+    }
+
+    #[test]
+    fn quit_add_task() {
+        let (dir, mut params) = session("quit", "call mom\n");
+        mock::script(&["+ add", "", ""]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), "call mom\n");
+        assert_eq!(mock::shown().len(), 3);
+    }
+
+    #[test]
+    fn search_tasks() {
+        let (dir, mut params) = session("search", "buy milk +home\nx 2026-10-01 call mom due:2026-09-30\n(A) call dad\n");
+        // Restoring the done task found by the search, the open task coming first
+        mock::script(&["🔍 search", "call", &mock::entry(2), "✔ mark as to do", "← back", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), "buy milk +home\n2026-10-01 call mom due:2026-09-30\n(A) call dad\n");
+
+        let shown = mock::shown();
+        assert_eq!(shown[1].prompt.as_deref(), Some("Search"));
+        assert!(shown[1].text_only);
+        assert_eq!(shown[2].entries, ["← back", "(A) call dad", "2026-09-30 : call mom"]);
+        assert_eq!(shown[3].entries[0], "✔ mark as to do");
+        assert_eq!(shown[4].entries.len(), 3);
+    }
+
+    #[test]
+    fn french_menus() {
+        let (dir, mut params) = session("french", "(B) call mom\n");
+        let rofi_config = RofiParams { lang : Lang::Fr, ..rofi_config() };
+        // The undo entry is shown after adding the task, the first task being the 9th entry
+        mock::script(&["+ ajouter", "pay rent", "✔ valider", &mock::entry(8), "* annuler", "* quitter"]);
+
+        assert!(show_main_menu(&rofi_config, &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        assert!(std::fs::read_to_string(dir.join("todo.txt")).unwrap().ends_with("pay rent\n"));
+
+        let shown = mock::shown();
+        assert_eq!(shown[0].prompt.as_deref(), Some("Tâches"));
+        assert_eq!(shown[0].entries[..2], ["+ ajouter", "~ terminées"]);
+        assert_eq!(shown[0].msg.as_deref(), Some("Alt+d : marquer comme faite   Alt+p : changer la priorité   Alt+r : supprimer   Alt+e : modifier"));
+        assert_eq!(shown[2].entries, ["✔ valider", "+ ajouter une date", "* annuler"]);
+        assert!(shown[4].msg.as_ref().unwrap().contains("\n𝐒𝐭𝐚𝐭𝐮𝐭 : À faire\n"));
+    }
+
+    #[test]
+    fn switch_and_move() {
+        let (dir, mut params) = session("lists", "call mom\n");
+        std::fs::write(dir.join("work.txt"), "write report\nfix bug\n").unwrap();
+        params.add_list(TaskList::load(String::from("work"), &dir.join("work.txt"), dir.join("done.txt"), 0, false).unwrap()).unwrap();
+        assert_eq!(params.add_list(TaskList::load(String::from("todo"), &dir.join("work.txt"), dir.join("done.txt"), 0, false).unwrap()).err(), Some(String::from("duplicated list: todo")));
+        mock::script(&["≡ lists", "work (2)", "write report", "→ move to list", "todo (1)", "≡ lists", "todo (2)", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        assert_eq!(params.list.name, "todo");
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), "call mom\nwrite report\n");
+        assert_eq!(std::fs::read_to_string(dir.join("work.txt")).unwrap(), "fix bug\n");
+
+        let shown = mock::shown();
+        assert_eq!(shown[0].prompt.as_deref(), Some("Todo (todo)"));
+        assert_eq!(shown[0].entries, [&MAIN_MENU[..], &["≡ lists", "* exit", "call mom"]].concat());
+        // The lists keep their order, the shown list being highlighted
+        assert_eq!(shown[1].entries, ["← back", "todo (1)", "work (2)", "all lists (3)"]);
+        assert_eq!(shown[1].highlighted, vec![(1, Highlight::Active)]);
+        assert_eq!(shown[2].prompt.as_deref(), Some("Todo (work)"));
+        assert!(shown[3].entries.contains(&String::from("→ move to list")));
+        // Only the other lists are shown to move the task
+        assert_eq!(shown[4].entries, ["← back", "todo (1)"]);
+        assert_eq!(shown[4].msg.as_deref(), Some("Moving a task to another list can't be undone"));
+        assert_eq!(shown[6].entries, ["← back", "todo (2)", "work (1)", "all lists (3)"]);
+        assert_eq!(shown[6].highlighted, vec![(2, Highlight::Active)]);
+        // The move isn't recorded in the history of either list
+        assert!(!shown[5].entries.contains(&String::from("↶ undo")));
+        assert!(!shown[7].entries.contains(&String::from("↶ undo")));
+    }
+
+    #[test]
+    fn combined_view() {
+        let (dir, mut params) = session("combined", "(B) call mom\nx 2026-10-01 pay rent\n");
+        std::fs::create_dir_all(dir.join("work")).unwrap();
+        std::fs::write(dir.join("work").join("todo.txt"), "(A) write report\nfix bug\n").unwrap();
+        params.add_list(TaskList::load(String::from("work"), &dir.join("work").join("todo.txt"), dir.join("work").join("done.txt"), 0, false).unwrap()).unwrap();
+        params.sort = SortTaskBy::Priority;
+        mock::script(&["≡ lists", "all lists (3)", "+ add", "buy milk", "✔ validate", "(A) write report", "✔ mark as done",
+                       "~ done", "~ archive done", "≡ lists", "work (1)", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        // Every task is saved and archived next to the file of its list, the new task being added to the main list
+        let today = Local::now().date_naive();
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), format!("(B) call mom\n{} buy milk\n", today));
+        assert_eq!(std::fs::read_to_string(dir.join("done.txt")).unwrap(), "x 2026-10-01 pay rent\n");
+        assert_eq!(std::fs::read_to_string(dir.join("work").join("todo.txt")).unwrap(), "fix bug\n");
+        assert_eq!(std::fs::read_to_string(dir.join("work").join("done.txt")).unwrap(), format!("x (A) {} {} write report\n", today, today));
+        // Leaving the combined view gives back the tasks to their list
+        assert_eq!(params.list.name, "work");
+        assert_eq!(params.list.todos.get_main_index().len(), 1);
+        assert_eq!(params.others[0].todos.get_main_index().len(), 2);
+
+        let shown = mock::shown();
+        // The tasks of both lists are sorted together
+        assert_eq!(shown[2].prompt.as_deref(), Some("Todo (all lists)"));
+        assert_eq!(shown[2].entries[8..], ["(A) write report", "(B) call mom", "fix bug"]);
+        let lists = &shown[shown.len() - 2];
+        assert_eq!(lists.entries, ["← back", "todo (2)", "work (1)", "all lists (3)"]);
+        assert_eq!(lists.highlighted, vec![(3, Highlight::Active)]);
+    }
+
+    #[test]
+    fn combined_history_and_recurrence() {
+        let (dir, mut params) = session("combined_history", "call mom\n");
+        std::fs::write(dir.join("work.txt"), "water plants due:2026-10-01 rec:+1w\n").unwrap();
+        params.add_list(TaskList::load(String::from("work"), &dir.join("work.txt"), dir.join("work.done.txt"), 0, false).unwrap()).unwrap();
+        // The combined view starts without operations to undo, its tasks being listed after the exit entry
+        mock::script(&["call mom", "✔ mark as done", "≡ lists", "all lists (1)", &mock::entry(8), "✔ mark as done", "≡ lists", "todo (0)", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        // The next occurrence of the recurring task is added to its list
+        let work = std::fs::read_to_string(dir.join("work.txt")).unwrap();
+        assert!(work.starts_with("x ") && work.ends_with(" water plants due:2026-10-08 rec:+1w\n"));
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap().lines().count(), 1);
+
+        let shown = mock::shown();
+        // The operations of the combined view are recorded in the history of their list, the main list keeping its own
+        assert!(!shown[4].entries.contains(&String::from("↶ undo")));
+        assert!(shown[6].entries.contains(&String::from("↶ undo")));
+        assert_eq!(shown[8].prompt.as_deref(), Some("Todo (todo)"));
+        assert!(shown[8].entries.contains(&String::from("↶ undo")));
+        assert!(params.others[0].history.can_undo());
+    }
+
+    #[test]
+    fn combined_undo_after_split() {
+        let (dir, mut params) = session("combined_undo", "call mom
+");
+        std::fs::write(dir.join("work.txt"), "write report\nfix bug\n").unwrap();
+        params.add_list(TaskList::load(String::from("work"), &dir.join("work.txt"), dir.join("work.done.txt"), 0, false).unwrap()).unwrap();
+        mock::script(&["≡ lists", "all lists (3)", "fix bug", "! remove", "write report", "! remove", "↶ undo",
+                       "≡ lists", "todo (1)", "≡ lists", "work (1)", "↶ undo", "* exit"]);
+
+        assert!(show_main_menu(&rofi_config(), &mut params) == Ok(MenuStatus::Exit));
+        assert!(mock::finished());
+        // The removals are undone in the list of the tasks, the main list being left untouched
+        assert_eq!(std::fs::read_to_string(dir.join("todo.txt")).unwrap(), "call mom\n");
+        let mut work = std::fs::read_to_string(dir.join("work.txt")).unwrap().lines().map(String::from).collect::<Vec<_>>();
+        work.sort();
+        assert_eq!(work, ["fix bug", "write report"]);
+
+        let shown = mock::shown();
+        assert_eq!(shown[9].prompt.as_deref(), Some("Todo (todo)"));
+        assert!(!shown[9].entries.contains(&String::from("↶ undo")));
+        assert_eq!(shown[11].prompt.as_deref(), Some("Todo (work)"));
+        assert!(shown[11].entries.contains(&String::from("↶ undo")));
+        // Both removals can be redone in the list
+        assert!(shown[12].entries.contains(&String::from("↷ redo")));
+        assert!(!params.list.history.can_undo());
+        params.list.redo().unwrap();
+        params.list.redo().unwrap();
+        assert!(params.list.todos.get_main_index().is_empty());
+    }
 }
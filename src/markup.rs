@@ -0,0 +1,207 @@
+use chrono::NaiveDate;
+use crate::task::Task;
+use crate::rofi::RofiParams;
+
+/// The colour of the overdue due dates
+const OVERDUE_COLOUR : &str = "#dc322f";
+/// The colour of the project tags
+const PROJECT_COLOUR : &str = "#268bd2";
+/// The colour of the context tags
+const CONTEXT_COLOUR : &str = "#2aa198";
+
+/// Escape a text so it is shown as is in Pango markup
+/// 
+/// Arguments:
+/// 
+/// * `text` - the text to escape
+pub fn escape(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => s.push_str("&amp;"),
+            '<' => s.push_str("&lt;"),
+            '>' => s.push_str("&gt;"),
+            '"' => s.push_str("&quot;"),
+            '\'' => s.push_str("&#39;"),
+            _ => s.push(c)
+        }
+    }
+    s
+}
+
+/// Get the colour of a priority, the priorities after C having no colour
+/// 
+/// Arguments:
+/// 
+/// * `priority` - the priority, from A to Z
+fn priority_colour(priority: char) -> Option<&'static str> {
+    match priority {
+        'A' => Some("#d33682"),
+        'B' => Some("#cb4b16"),
+        'C' => Some("#b58900"),
+        _ => None
+    }
+}
+
+/// Show a priority in bold, in its colour
+/// 
+/// Arguments:
+/// 
+/// * `priority` - the priority, from A to Z
+fn priority(priority: char) -> String {
+    match priority_colour(priority) {
+        Some(colour) => format!("<span weight=\"bold\" foreground=\"{}\">({})</span>", colour, priority),
+        None => format!("<b>({})</b>", priority)
+    }
+}
+
+/// Show a due date, in red when it is overdue
+/// 
+/// Arguments:
+/// 
+/// * `task` - the task
+/// * `date` - the due date of the task
+/// * `today` - the current date
+/// * `date_format` - the format of the date
+fn due_date(task: &Task, date: NaiveDate, today: NaiveDate, date_format: &str) -> String {
+    if task.is_overdue(today) {
+        format!("<span foreground=\"{}\">{}</span>", OVERDUE_COLOUR, escape(&date.format(date_format).to_string()))
+    } else {
+        escape(&date.format(date_format).to_string())
+    }
+}
+
+/// Escape the content of a task, showing its project and context tags in their colour
+/// 
+/// Arguments:
+/// 
+/// * `content` - the content of the task
+fn content(content: &str) -> String {
+    content.split(' ')
+        .map(|word| {
+            let colour = match word.chars().next() {
+                Some('+') if word.len() > 1 => Some(PROJECT_COLOUR),
+                Some('@') if word.len() > 1 => Some(CONTEXT_COLOUR),
+                _ => None
+            };
+            match colour {
+                Some(colour) => format!("<span style=\"italic\" foreground=\"{}\">{}</span>", colour, escape(word)),
+                None => escape(word)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Show a task in a list, like its `Display` with Pango markup
+/// 
+/// Completed tasks are struck through
+/// 
+/// Arguments:
+/// 
+/// * `task` - the task
+/// * `today` - the current date, to find overdue tasks
+/// * `date_format` - the format of the due date
+pub fn task_row(task: &Task, today: NaiveDate, date_format: &str) -> String {
+    let mut s = String::new();
+    if let Some(p) = task.priority {
+        s.push_str(&priority(p));
+        s.push(' ');
+    }
+    if let Some(date) = *task.get_due() {
+        s.push_str(&format!("{} : ", due_date(task, date, today, date_format)));
+    }
+    s.push_str(&content(task.get_content()));
+    if task.completion {
+        format!("<s>{}</s>", s)
+    } else {
+        s
+    }
+}
+
+/// Show a complete description of the task, like `Task::recap_str` with Pango markup
+/// 
+/// Arguments:
+/// 
+/// * `task` - the task
+/// * `today` - the current date, to find overdue tasks
+/// * `rofi_config` - the Rofi configuration, giving the labels and the format of the dates
+pub fn task_recap(task: &Task, today: NaiveDate, rofi_config: &RofiParams) -> String {
+    let label = |key| escape(rofi_config.tr(key));
+    let date_format = &rofi_config.date_format;
+    let mut s = format!("<b>{}</b> : {}", label("recap.task"), content(task.get_content()));
+    if task.completion {
+        s.push_str(&format!("\n<b>{}</b> : <s>{}</s>", label("recap.status"), label("recap.done")));
+        if let Some(date) = task.completion_date {
+            s.push_str(&format!(" ({})", escape(&date.format(date_format).to_string())));
+        }
+    } else {
+        s.push_str(&format!("\n<b>{}</b> : {}", label("recap.status"), label("recap.todo")));
+    }
+    if let Some(p) = task.priority {
+        s.push_str(&format!("\n<b>{}</b> : {}", label("recap.priority"), priority(p)));
+    }
+    if let Some(date) = task.creation_date {
+        s.push_str(&format!("\n<b>{}</b> : {}", label("recap.created"), escape(&date.format(date_format).to_string())));
+    }
+    if let Some(date) = *task.get_due() {
+        s.push_str(&format!("\n<b>{}</b> : {}", label("recap.due"), due_date(task, date, today, date_format)));
+    }
+    if let Some(date) = *task.get_threshold() {
+        s.push_str(&format!("\n<b>{}</b> : {}", label("recap.threshold"), escape(&date.format(date_format).to_string())));
+    }
+    if !task.get_context_tags().is_empty() {
+        s.push_str(&format!("\n<b>{}</b> : {}", label("recap.contexts"), escape(&task.get_context_tags().join(", "))));
+    }
+    if !task.get_project_tags().is_empty() {
+        s.push_str(&format!("\n<b>{}</b> : {}", label("recap.projects"), escape(&task.get_project_tags().join(", "))));
+    }
+    s
+}
+
+#[cfg(test)]
+mod markup_tests {
+    use super::*;
+    use crate::i18n::Lang;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn escape_text() {
+        assert_eq!(escape("<b>R&D</b> \"it's\""), "&lt;b&gt;R&amp;D&lt;/b&gt; &quot;it&#39;s&quot;");
+        assert_eq!(escape("call mom"), "call mom");
+    }
+
+    #[test]
+    fn rows() {
+        let today = date("2026-10-18");
+        let task = Task::from_todotxt(String::from("(A) fix <b> & +Work @pc due:2026-10-01")).unwrap();
+        assert_eq!(task_row(&task, today, "%Y-%m-%d"), "<span weight=\"bold\" foreground=\"#d33682\">(A)</span> \
+            <span foreground=\"#dc322f\">2026-10-01</span> : fix &lt;b&gt; &amp; \
+            <span style=\"italic\" foreground=\"#268bd2\">+Work</span> \
+            <span style=\"italic\" foreground=\"#2aa198\">@pc</span>");
+
+        let task = Task::from_todotxt(String::from("x (D) 2026-10-02 a + b due:2026-10-01")).unwrap();
+        assert_eq!(task_row(&task, today, "%Y-%m-%d"), "<s><b>(D)</b> 2026-10-01 : a + b</s>");
+    }
+
+    #[test]
+    fn recap() {
+        let task = Task::from_todotxt(String::from("(B) 2026-10-01 a <task> @home")).unwrap();
+        let recap = task_recap(&task, date("2026-10-18"), &RofiParams::default());
+        assert!(recap.starts_with("<b>Task</b> : a &lt;task&gt; <span"));
+        assert!(recap.contains("\n<b>Status</b> : To do"));
+        assert!(recap.contains("\n<b>Priority</b> : <span weight=\"bold\" foreground=\"#cb4b16\">(B)</span>"));
+        assert!(recap.contains("\n<b>Created on</b> : 2026-10-01"));
+        assert!(recap.ends_with("\n<b>Context tags</b> : home"));
+        let recap = task_recap(&task, date("2026-10-18"), &RofiParams { lang : Lang::Fr, date_format : String::from("%d/%m/%Y"), ..RofiParams::default() });
+        assert!(recap.contains("\n<b>Statut</b> : À faire"));
+        assert!(recap.contains("\n<b>Créée le</b> : 01/10/2026"));
+        // The labels replaced in the configuration file are used, and escaped
+        let messages = std::collections::HashMap::from([(String::from("recap.todo"), String::from("<open>"))]);
+        let recap = task_recap(&task, date("2026-10-18"), &RofiParams { messages, ..RofiParams::default() });
+        assert!(recap.contains("\n<b>Status</b> : &lt;open&gt;"));
+    }
+}
@@ -0,0 +1,392 @@
+use std::io::{ErrorKind, Write};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::str::FromStr;
+use crate::rofi::{Rofi, RofiError, RofiParams};
+use crate::launchers::{Dmenu, Wofi, Fuzzel, Bemenu, Fzf};
+
+/// The program used to show the menus
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Rofi,
+    Dmenu,
+    Wofi,
+    Fuzzel,
+    Bemenu,
+    Fzf,
+    /// Replays scripted selections, for tests
+    #[cfg(test)]
+    Mock
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "rofi" => Ok(Backend::Rofi),
+            "dmenu" => Ok(Backend::Dmenu),
+            "wofi" => Ok(Backend::Wofi),
+            "fuzzel" => Ok(Backend::Fuzzel),
+            "bemenu" => Ok(Backend::Bemenu),
+            "fzf" => Ok(Backend::Fzf),
+            _ => Err(format!("unknown backend: {}", s))
+        }
+    }
+}
+
+impl Backend {
+    /// Does the program render Pango markup
+    pub fn renders_markup(&self) -> bool {
+        match self {
+            Backend::Rofi => true,
+            #[cfg(test)]
+            Backend::Mock => true,
+            _ => false
+        }
+    }
+
+    /// Does the program show the entries in a grid
+    pub fn shows_grid(&self) -> bool {
+        match self {
+            Backend::Rofi => true,
+            #[cfg(test)]
+            Backend::Mock => true,
+            _ => false
+        }
+    }
+}
+
+/// How a row of a menu is highlighted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Highlight {
+    /// An important row
+    Active,
+    /// A row needing attention
+    Urgent
+}
+
+impl Highlight {
+    /// The marker put before the row by the launchers which can't highlight it
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Highlight::Active => "★ ",
+            Highlight::Urgent => "‼ "
+        }
+    }
+}
+
+/// The choice of the user in a menu
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selection {
+    /// The index of the selected entry, `None` if the user typed a text matching no entry or quitted
+    pub index : Option<usize>,
+    /// The selected entry or the text typed by the user, empty if the user quitted
+    pub text : String,
+    /// The exit code of the launcher
+    pub code : i32
+}
+
+impl Selection {
+    /// Create a selection from the text returned by a launcher which doesn't give the index of the entry
+    /// 
+    /// The index is the one of the first entry equal to the text
+    /// 
+    /// Arguments:
+    /// 
+    /// * `text` - the text returned by the launcher
+    /// * `entries` - the entries shown
+    /// * `code` - the exit code of the launcher
+    pub fn from_text(text: String, entries: &[String], code: i32) -> Self {
+        Selection { index : entries.iter().position(|e| *e == text), text, code }
+    }
+
+    /// Take the selected item, when the items are shown after other entries
+    /// 
+    /// The other items are dropped
+    /// 
+    /// Arguments:
+    /// 
+    /// * `offset` - the number of entries shown before the items
+    /// * `items` - the items, in the order of their entries
+    pub fn item<T>(&self, offset: usize, items: Vec<T>) -> Option<T> {
+        items.into_iter().nth(self.index?.checked_sub(offset)?)
+    }
+
+    /// Get the number of the custom keybinding used to end the menu, if any
+    /// 
+    /// Rofi exits with the code 10 for the custom keybinding 1, up to 28 for the custom keybinding 19
+    pub fn custom_key(&self) -> Option<usize> {
+        match self.code {
+            10..=28 => Some(self.code as usize - 9),
+            _ => None
+        }
+    }
+}
+
+/// A menu shown by a launcher (rofi, dmenu, fzf...)
+/// 
+/// A launcher lacking a feature ignores it, the menu being still usable without it
+pub trait Menu {
+    /// Change the prompt
+    /// 
+    /// Arguments:
+    /// 
+    /// * `p` - the `String` to prompt
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu;
+
+    /// Print a message under the prompt
+    /// 
+    /// Arguments:
+    /// 
+    /// * `m` - the `String` to display
+    fn msg(&mut self, m: String) -> &mut dyn Menu;
+
+    /// Pre-enter an input text
+    /// 
+    /// Arguments:
+    /// 
+    /// * `text` - the `String` to input
+    fn pretext(&mut self, text: String) -> &mut dyn Menu;
+
+    /// Select a specific line
+    /// 
+    /// Arguments:
+    /// 
+    /// * `index` - the index of the line
+    fn selected(&mut self, index: u32) -> &mut dyn Menu;
+
+    /// Mark a range of index as active
+    /// 
+    /// Arguments:
+    /// 
+    /// * `start` - the index of the first active line
+    /// * `end` - the index of the last active line
+    fn select_range(&mut self, start: usize, end: usize) -> &mut dyn Menu;
+
+    /// Highlight some rows, or put a marker before them if the launcher can't highlight them
+    /// 
+    /// Arguments:
+    /// 
+    /// * `rows` - the indexes of the rows
+    /// * `highlight` - how the rows are highlighted
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu;
+
+    /// The placeholder to put in the input zone
+    /// 
+    /// Arguments:
+    /// 
+    /// * `placeholder` - the placeholder to show
+    fn placeholder(&mut self, placeholder: &str) -> &mut dyn Menu;
+
+    /// Mask lines under the input
+    fn text_only(&mut self) -> &mut dyn Menu;
+
+    /// Render the entries and the message as Pango markup
+    fn markup(&mut self) -> &mut dyn Menu;
+
+    /// Show the entries in a grid, row by row
+    /// 
+    /// Arguments:
+    /// 
+    /// * `columns` - the number of columns
+    /// * `rows` - the number of rows
+    fn grid(&mut self, columns: usize, rows: usize) -> &mut dyn Menu;
+
+    /// Bind a key to a custom keybinding, ending the menu with the highlighted entry
+    /// 
+    /// Arguments:
+    /// 
+    /// * `custom` - the number of the custom keybinding, from 1 to 19
+    /// * `key` - the key, such as `Alt+d`
+    fn custom_key(&mut self, custom: usize, key: &str) -> &mut dyn Menu;
+
+    /// Show the menu with a list of entries
+    /// 
+    /// Returns the selected entry or the text typed by the user, `RofiError::Cancelled` if the user quitted
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - a vector of `String` to display as options
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError>;
+
+    /// Show the menu with a list of entries
+    /// 
+    /// Returns the selected entry or the text typed by the user, an empty selection if the user quitted
+    /// 
+    /// Arguments:
+    /// 
+    /// * `entries` - a vector of `String` to display as options
+    fn run(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        match self.show(entries) {
+            Err(RofiError::Cancelled) => Ok(Selection::default()),
+            result => result
+        }
+    }
+}
+
+/// Create a menu with the launcher chosen by the user
+/// 
+/// Arguments:
+/// 
+/// * `p` - a reference to a `RofiParams` struct
+pub fn new(p : &RofiParams) -> Box<dyn Menu> {
+    match p.backend {
+        Backend::Rofi => Box::new(Rofi::from(p)),
+        Backend::Dmenu => Box::new(Dmenu::from(p)),
+        Backend::Wofi => Box::new(Wofi::from(p)),
+        Backend::Fuzzel => Box::new(Fuzzel::from(p)),
+        Backend::Bemenu => Box::new(Bemenu::from(p)),
+        Backend::Fzf => Box::new(Fzf::from(p)),
+        #[cfg(test)]
+        Backend::Mock => Box::new(crate::mock::MockMenu::default())
+    }
+}
+
+/// Launch a menu program, writing the entries to its standard input
+/// 
+/// Arguments:
+/// 
+/// * `command` - the command launching the program
+/// * `entries` - the entries to write, one per line
+pub fn launch(command: &mut Command, entries: &[String]) -> Result<Output, RofiError> {
+    let mut proc = match command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn() {
+        Ok(proc) => proc,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(RofiError::NotInstalled(command.get_program().to_string_lossy().into_owned())),
+        Err(e) => return Err(RofiError::Io(e.to_string()))
+    };
+
+    let entry_list = vec2str(entries);
+    if let Some(stdin) = proc.stdin.as_mut() {
+        match stdin.write_all(entry_list.as_bytes()) {
+            // The program may exit before reading every entry, its exit code tells why
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(RofiError::Io(e.to_string())),
+            _ => ()
+        }
+    }
+
+    proc.wait_with_output().map_err(|e| RofiError::Io(e.to_string()))
+}
+
+/// Get the exit code of a menu program
+/// 
+/// Arguments:
+/// 
+/// * `status` - the exit status of the program
+pub fn exit_code(status: ExitStatus) -> Result<i32, RofiError> {
+    status.code().ok_or(RofiError::Killed)
+}
+
+/// Get the text printed by a menu program, without its trailing newline
+/// 
+/// Arguments:
+/// 
+/// * `stdout` - the output of the program
+pub fn output_text(stdout: Vec<u8>) -> Result<String, RofiError> {
+    let mut text = String::from_utf8(stdout).map_err(|_| RofiError::NotUtf8)?;
+    trim_newline(&mut text);
+    Ok(text)
+}
+
+/// Remove the trailing newlines of a String
+/// 
+/// Arguments:
+/// 
+/// * `s` - the `String`
+pub fn trim_newline(s: &mut String) {
+    if s.ends_with('\n') {
+        s.pop();
+        if s.ends_with('\r') {
+            s.pop();
+        }
+    }
+}
+
+/// Write ranges of rows the way Rofi and other launchers expect them, such as `0-5,7,9-10`
+/// 
+/// Arguments:
+/// 
+/// * `ranges` - the first and last rows of each range
+pub fn rows_list(ranges: &[(usize, usize)]) -> String {
+    ranges.iter()
+        .map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Transform a `Vec<String>` to a String with line breaks between each entry
+/// 
+/// Arguments:
+/// 
+/// * `tab` - the `Vec<String>` of entries
+fn vec2str(tab : &[String]) -> String {
+    let mut s = String::new();
+    for x in tab {
+        s.push_str(x);
+        s.push('\n');
+    }
+    s
+}
+
+#[cfg(test)]
+mod menu_tests {
+    use super::*;
+
+    #[test]
+    fn trim_newline_test() {
+        let mut test_string = String::from("Hello\n");
+        trim_newline(&mut test_string);
+        assert_eq!(test_string, "Hello");
+        let mut test_string2 = String::from("Hi!");
+        trim_newline(&mut test_string2);
+        assert_eq!(test_string2, "Hi!");
+        let mut test_string3 = String::from("foobar\r\n");
+        trim_newline(&mut test_string3);
+        assert_eq!(test_string3, "foobar");
+    }
+
+    #[test]
+    fn vec2str_test() {
+        let test_vec = vec![String::from("foo"), String::from("bar")];
+        assert_eq!(vec2str(&test_vec), String::from("foo\nbar\n"));
+    }
+
+    #[test]
+    fn selection() {
+        let entries = vec![String::from("← back"), String::from("a task"), String::from("a task")];
+        let selection = Selection::from_text(String::from("a task"), &entries, 0);
+        assert_eq!(selection.index, Some(1));
+        assert_eq!(Selection::from_text(String::from("new task"), &entries, 0).index, None);
+
+        let tasks = vec!["first", "second"];
+        assert_eq!(selection.item(1, tasks.clone()), Some("first"));
+        assert_eq!(Selection { index : Some(2), ..selection.clone() }.item(1, tasks.clone()), Some("second"));
+        assert_eq!(Selection { index : Some(0), ..selection.clone() }.item(1, tasks.clone()), None);
+        assert_eq!(Selection { index : Some(3), ..selection.clone() }.item(1, tasks.clone()), None);
+        assert_eq!(Selection::default().item(1, tasks), None);
+
+        assert_eq!(Selection { code : 10, ..selection.clone() }.custom_key(), Some(1));
+        assert_eq!(Selection { code : 28, ..selection.clone() }.custom_key(), Some(19));
+        assert_eq!(Selection { code : 1, ..selection }.custom_key(), None);
+    }
+
+    #[test]
+    fn launch_errors() {
+        let error = launch(&mut Command::new("rofitodo-missing-launcher"), &[]).unwrap_err();
+        assert_eq!(error, RofiError::NotInstalled(String::from("rofitodo-missing-launcher")));
+        assert_eq!(output_text(vec![0xff, b'\n']), Err(RofiError::NotUtf8));
+        assert_eq!(output_text(b"call mom\n".to_vec()), Ok(String::from("call mom")));
+    }
+
+    #[test]
+    fn rows_ranges() {
+        assert_eq!(rows_list(&[]), "");
+        assert_eq!(rows_list(&[(0, 5), (7, 7), (9, 10)]), "0-5,7,9-10");
+    }
+
+    #[test]
+    fn parse_backend() {
+        assert_eq!("rofi".parse::<Backend>(), Ok(Backend::Rofi));
+        assert_eq!("Fuzzel".parse::<Backend>(), Ok(Backend::Fuzzel));
+        assert!("xmenu".parse::<Backend>().is_err());
+    }
+}
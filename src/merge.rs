@@ -0,0 +1,238 @@
+/// Where a line of a merged file comes from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Origin {
+    /// The n-th line of our version
+    Ours(usize),
+    /// The n-th line of their version
+    Theirs(usize)
+}
+
+/// A part of a file changed differently in both versions
+pub struct Conflict {
+    /// The lines of the common ancestor
+    pub base : Vec<String>,
+    /// The lines of our version
+    pub ours : Vec<String>,
+    /// The lines of their version
+    pub theirs : Vec<String>
+}
+
+/// How to resolve a conflict
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    /// Keep the lines of our version
+    Ours,
+    /// Keep the lines of their version
+    Theirs,
+    /// Keep their lines followed by ours
+    Both
+}
+
+/// Merge two versions of a file with their common ancestor, line by line
+/// 
+/// Lines changed in only one version are taken from this version.
+/// Lines only added in one version around lines changed in the other version are kept with the changed lines,
+/// and lines added at the same place in both versions are all kept, their lines first.
+/// Any other part changed differently in both versions is a conflict, solved by `resolve`.
+/// 
+/// Returns the origin of each line of the merged file
+/// 
+/// Arguments:
+/// 
+/// * `base` - the lines of the common ancestor
+/// * `ours` - the lines of our version
+/// * `theirs` - the lines of their version
+/// * `resolve` - a closure choosing how to resolve a conflict
+pub fn merge3(base: &[&str], ours: &[&str], theirs: &[&str], mut resolve: impl FnMut(&Conflict) -> Resolution) -> Vec<Origin> {
+    let ours_matches = lcs(base, ours);
+    let theirs_matches = lcs(base, theirs);
+    let mut merged = Vec::<Origin>::new();
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // The next line of the ancestor kept in both versions
+        let sync = (i..base.len()).find_map(|b| match (ours_matches[b], theirs_matches[b]) {
+            (Some(o), Some(t)) => Some((b, o, t)),
+            _ => None
+        });
+        let (b, o, t) = sync.unwrap_or((base.len(), ours.len(), theirs.len()));
+        let (base_chunk, ours_chunk, theirs_chunk) = (&base[i..b], &ours[j..o], &theirs[k..t]);
+        if theirs_chunk == base_chunk || ours_chunk == theirs_chunk {
+            merged.extend((j..o).map(Origin::Ours));
+        } else if ours_chunk == base_chunk {
+            merged.extend((k..t).map(Origin::Theirs));
+        } else if base_chunk.is_empty() {
+            merged.extend((k..t).map(Origin::Theirs));
+            merged.extend((j..o).map(Origin::Ours));
+        } else if let Some(p) = find(ours_chunk, base_chunk) {
+            // We only added lines around the lines they changed
+            merged.extend((j..j + p).map(Origin::Ours));
+            merged.extend((k..t).map(Origin::Theirs));
+            merged.extend((j + p + base_chunk.len()..o).map(Origin::Ours));
+        } else if let Some(p) = find(theirs_chunk, base_chunk) {
+            // They only added lines around the lines we changed
+            merged.extend((k..k + p).map(Origin::Theirs));
+            merged.extend((j..o).map(Origin::Ours));
+            merged.extend((k + p + base_chunk.len()..t).map(Origin::Theirs));
+        } else {
+            let to_strings = |lines: &[&str]| lines.iter().map(|l| String::from(*l)).collect::<Vec<_>>();
+            let resolution = resolve(&Conflict { base : to_strings(base_chunk), ours : to_strings(ours_chunk), theirs : to_strings(theirs_chunk) });
+            if resolution != Resolution::Ours {
+                merged.extend((k..t).map(Origin::Theirs));
+            }
+            if resolution != Resolution::Theirs {
+                merged.extend((j..o).map(Origin::Ours));
+            }
+        }
+        if sync.is_none() {
+            return merged;
+        }
+        merged.push(Origin::Ours(o));
+        i = b + 1;
+        j = o + 1;
+        k = t + 1;
+    }
+}
+
+/// Find the position of a non-empty sequence of lines inside another one
+/// 
+/// Arguments:
+/// 
+/// * `lines` - the lines to search in
+/// * `sequence` - the lines to find
+fn find(lines: &[&str], sequence: &[&str]) -> Option<usize> {
+    lines.windows(sequence.len()).position(|window| window == sequence)
+}
+
+/// Find a longest common subsequence of lines between two versions
+/// 
+/// Returns for each line of `a` the index of the matching line in `b`, if any
+/// 
+/// Arguments:
+/// 
+/// * `a` - the lines of the first version
+/// * `b` - the lines of the second version
+fn lcs(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; a.len()];
+    // Skipping the common beginning and end to keep the table small
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    for (n, m) in matches.iter_mut().enumerate().take(prefix) {
+        *m = Some(n);
+    }
+    for n in 1..=suffix {
+        matches[a.len() - n] = Some(b.len() - n);
+    }
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    // table[x][y] is the length of the longest common subsequence of a_mid[x..] and b_mid[y..]
+    let mut table = vec![vec![0usize; b_mid.len() + 1]; a_mid.len() + 1];
+    for x in (0..a_mid.len()).rev() {
+        for y in (0..b_mid.len()).rev() {
+            table[x][y] = if a_mid[x] == b_mid[y] { table[x + 1][y + 1] + 1 } else { table[x + 1][y].max(table[x][y + 1]) };
+        }
+    }
+    let (mut x, mut y) = (0, 0);
+    while x < a_mid.len() && y < b_mid.len() {
+        if a_mid[x] == b_mid[y] {
+            matches[prefix + x] = Some(prefix + y);
+            x += 1;
+            y += 1;
+        } else if table[x + 1][y] >= table[x][y + 1] {
+            x += 1;
+        } else {
+            y += 1;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Merge and return the merged lines, panicking on conflicts
+    fn merge(base: &[&str], ours: &[&str], theirs: &[&str]) -> Vec<String> {
+        merge3(base, ours, theirs, |_| panic!("unexpected conflict"))
+            .into_iter()
+            .map(|origin| match origin {
+                Origin::Ours(n) => String::from(ours[n]),
+                Origin::Theirs(n) => String::from(theirs[n])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn longest_common_subsequence() {
+        assert_eq!(lcs(&["a", "b", "c", "d"], &["a", "c", "d", "e"]), vec![Some(0), None, Some(1), Some(2)]);
+        assert_eq!(lcs(&["a", "b"], &[]), vec![None, None]);
+        assert_eq!(lcs(&["x", "a", "y", "b"], &["a", "b", "z"]), vec![None, Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn merge_independent_changes() {
+        let base = ["a", "b", "c", "d"];
+        // We edit the first line, they remove the third one and add a line at the end
+        let ours = ["A", "b", "c", "d"];
+        let theirs = ["a", "b", "d", "e"];
+        assert_eq!(merge(&base, &ours, &theirs), vec!["A", "b", "d", "e"]);
+        // The same change on both sides
+        assert_eq!(merge(&base, &theirs, &theirs), vec!["a", "b", "d", "e"]);
+    }
+
+    #[test]
+    fn merge_additions() {
+        let base = ["a"];
+        assert_eq!(merge(&base, &["a", "ours"], &["a", "theirs"]), vec!["a", "theirs", "ours"]);
+        assert_eq!(merge(&[], &["ours"], &["theirs"]), vec!["theirs", "ours"]);
+    }
+
+    #[test]
+    fn merge_origins() {
+        let origins = merge3(&["a", "b"], &["a", "b", "c"], &["a", "B"], |_| panic!("unexpected conflict"));
+        assert_eq!(origins, vec![Origin::Ours(0), Origin::Theirs(1), Origin::Ours(2)]);
+    }
+
+    #[test]
+    fn merge_conflicts() {
+        let base = ["a", "b", "c"];
+        let ours = ["a", "our b", "c"];
+        let theirs = ["a", "their b", "c"];
+        let mut conflicts = 0;
+        let origins = merge3(&base, &ours, &theirs, |conflict| {
+            conflicts += 1;
+            assert_eq!(conflict.base, vec!["b"]);
+            assert_eq!(conflict.ours, vec!["our b"]);
+            assert_eq!(conflict.theirs, vec!["their b"]);
+            Resolution::Theirs
+        });
+        assert_eq!(conflicts, 1);
+        assert_eq!(origins, vec![Origin::Ours(0), Origin::Theirs(1), Origin::Ours(2)]);
+
+        let origins = merge3(&base, &ours, &theirs, |_| Resolution::Ours);
+        assert_eq!(origins, vec![Origin::Ours(0), Origin::Ours(1), Origin::Ours(2)]);
+        let origins = merge3(&base, &ours, &theirs, |_| Resolution::Both);
+        assert_eq!(origins, vec![Origin::Ours(0), Origin::Theirs(1), Origin::Ours(1), Origin::Ours(2)]);
+
+        // Added on one side around a line edited on the other side
+        let origins = merge3(&base, &["a", "b", "new", "c"], &theirs, |_| panic!("unexpected conflict"));
+        assert_eq!(origins, vec![Origin::Ours(0), Origin::Theirs(1), Origin::Ours(2), Origin::Ours(3)]);
+
+        // Removed on one side, edited on the other
+        let origins = merge3(&base, &["a", "c"], &theirs, |conflict| {
+            assert!(conflict.ours.is_empty());
+            Resolution::Ours
+        });
+        assert_eq!(origins, vec![Origin::Ours(0), Origin::Ours(1)]);
+    }
+
+    proptest! {
+        #[test]
+        fn merge_one_sided_changes(base in prop::collection::vec("[a-c]", 0..8), changed in prop::collection::vec("[a-d]", 0..8)) {
+            let base = base.iter().map(String::as_str).collect::<Vec<_>>();
+            let changed = changed.iter().map(String::as_str).collect::<Vec<_>>();
+            prop_assert_eq!(merge(&base, &changed, &base), changed.clone());
+            prop_assert_eq!(merge(&base, &base, &changed), changed);
+        }
+    }
+}
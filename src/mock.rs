@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use crate::menu::{Highlight, Menu, Selection};
+use crate::rofi::RofiError;
+
+/// A menu shown to the user, as recorded by the mock backend
+#[derive(Clone, Debug, Default)]
+pub struct Shown {
+    pub prompt : Option<String>,
+    pub msg : Option<String>,
+    pub pretext : Option<String>,
+    pub selected : Option<u32>,
+    pub active : Option<(usize, usize)>,
+    pub highlighted : Vec<(usize, Highlight)>,
+    pub placeholder : Option<String>,
+    pub text_only : bool,
+    pub markup : bool,
+    pub grid : Option<(usize, usize)>,
+    /// The custom keybindings, with their number
+    pub keys : Vec<(usize, String)>,
+    pub entries : Vec<String>
+}
+
+/// The start of a scripted selection giving the index of the entry
+const INDEX_MARKER : char = '\u{0}';
+/// A scripted selection making the menu fail
+const KILLED_MARKER : &str = "\u{1}";
+
+thread_local! {
+    /// The selections made by the user, in order
+    static SCRIPT : RefCell<VecDeque<String>> = const { RefCell::new(VecDeque::new()) };
+    /// The menus shown to the user, in order
+    static SHOWN : RefCell<Vec<Shown>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Set the selections made by the user in the next menus, and forget the menus already shown
+/// 
+/// An empty selection is the user quitting the menu, `killed` makes the menu fail
+/// 
+/// Arguments:
+/// 
+/// * `selections` - the selected entries or typed texts, in order
+pub fn script(selections: &[&str]) {
+    SCRIPT.with(|script| *script.borrow_mut() = selections.iter().map(|s| String::from(*s)).collect());
+    SHOWN.with(|shown| shown.borrow_mut().clear());
+}
+
+/// A scripted selection selecting an entry by its index, to tell apart entries with the same text
+/// 
+/// Arguments:
+/// 
+/// * `index` - the index of the entry
+pub fn entry(index: usize) -> String {
+    format!("{}{}", INDEX_MARKER, index)
+}
+
+/// A scripted selection using a custom keybinding on an entry
+/// 
+/// Arguments:
+/// 
+/// * `custom` - the number of the custom keybinding
+/// * `index` - the index of the highlighted entry
+pub fn key(custom: usize, index: usize) -> String {
+    format!("{}{}{}{}", INDEX_MARKER, index, INDEX_MARKER, custom)
+}
+
+/// A scripted selection making the menu fail, as if the launcher was killed
+pub fn killed() -> String {
+    String::from(KILLED_MARKER)
+}
+
+/// Get the menus shown since the selections were scripted
+pub fn shown() -> Vec<Shown> {
+    SHOWN.with(|shown| shown.borrow().clone())
+}
+
+/// Is every scripted selection made
+pub fn finished() -> bool {
+    SCRIPT.with(|script| script.borrow().is_empty())
+}
+
+/// A menu replaying scripted selections instead of launching a program
+/// 
+/// A scripted selection selects the first entry equal to it, if any, or the entry given by `entry`.
+/// The menus are run on the current thread, each test thread having its own script
+#[derive(Default)]
+pub struct MockMenu {
+    shown : Shown
+}
+
+impl Menu for MockMenu {
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu {
+        self.shown.prompt = Some(String::from(p));
+        self
+    }
+
+    fn msg(&mut self, m: String) -> &mut dyn Menu {
+        self.shown.msg = Some(m);
+        self
+    }
+
+    fn pretext(&mut self, text: String) -> &mut dyn Menu {
+        self.shown.pretext = Some(text);
+        self
+    }
+
+    fn selected(&mut self, index: u32) -> &mut dyn Menu {
+        self.shown.selected = Some(index);
+        self
+    }
+
+    fn select_range(&mut self, start: usize, end: usize) -> &mut dyn Menu {
+        self.shown.active = Some((start, end));
+        self
+    }
+
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu {
+        self.shown.highlighted.extend(rows.iter().map(|row| (*row, highlight)));
+        self
+    }
+
+    fn placeholder(&mut self, placeholder: &str) -> &mut dyn Menu {
+        self.shown.placeholder = Some(String::from(placeholder));
+        self
+    }
+
+    fn text_only(&mut self) -> &mut dyn Menu {
+        self.shown.text_only = true;
+        self
+    }
+
+    fn markup(&mut self) -> &mut dyn Menu {
+        self.shown.markup = true;
+        self
+    }
+
+    fn grid(&mut self, columns: usize, rows: usize) -> &mut dyn Menu {
+        self.shown.grid = Some((columns, rows));
+        self
+    }
+
+    fn custom_key(&mut self, custom: usize, key: &str) -> &mut dyn Menu {
+        self.shown.keys.push((custom, String::from(key)));
+        self
+    }
+
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        self.shown.entries = entries;
+        let shown = std::mem::take(&mut self.shown);
+        let selection = SCRIPT.with(|script| script.borrow_mut().pop_front());
+        match selection {
+            Some(selection) => {
+                if selection.is_empty() || selection == KILLED_MARKER {
+                    SHOWN.with(|s| s.borrow_mut().push(shown));
+                    return Err(if selection.is_empty() { RofiError::Cancelled } else { RofiError::Killed });
+                }
+                let selection = match selection.strip_prefix(INDEX_MARKER) {
+                    Some(index) => {
+                        // Rofi exits with the code 10 for the custom keybinding 1
+                        let (index, code) = match index.split_once(INDEX_MARKER) {
+                            Some((index, custom)) => (index, custom.parse::<i32>().unwrap() + 9),
+                            None => (index, 0)
+                        };
+                        let index = index.parse::<usize>().unwrap();
+                        Selection { index : Some(index), text : shown.entries[index].clone(), code }
+                    },
+                    None => Selection::from_text(selection, &shown.entries, 0)
+                };
+                SHOWN.with(|s| s.borrow_mut().push(shown));
+                Ok(selection)
+            },
+            None => panic!("No scripted selection left for the menu {:?}", shown)
+        }
+    }
+}
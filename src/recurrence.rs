@@ -0,0 +1,124 @@
+use chrono::{NaiveDate, Duration, Months, Datelike, Weekday};
+use regex::Regex;
+use lazy_static::lazy_static;
+
+/// The unit of a recurrence interval
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecurrenceUnit {
+    Day,
+    BusinessDay,
+    Week,
+    Month,
+    Year
+}
+
+/// A recurrence rule following the todo.txt `rec:` extension
+/// 
+/// `rec:1w` repeats one week after the completion date, `rec:+1w` repeats one week
+/// after the previous due date
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Recurrence {
+    /// Is the next occurrence computed from the previous due date instead of the completion date
+    pub strict : bool,
+    /// The number of units between two occurrences
+    pub amount : u32,
+    /// The unit of the interval
+    pub unit : RecurrenceUnit
+}
+
+impl Recurrence {
+    /// Parse the value of a `rec:` tag (`1w`, `+3d`, `2m`...)
+    /// 
+    /// Arguments:
+    /// 
+    /// * `rec` - the value of the tag
+    pub fn parse(rec: &str) -> Result<Self, String> {
+        lazy_static! {
+            static ref RE_REC : Regex = Regex::new(r"^(?P<strict>\+)?(?P<amount>\d+)?(?P<unit>[dbwmy])$").unwrap();
+        }
+        let cap = match RE_REC.captures(rec) {
+            Some(cap) => cap,
+            None => return Err(format!("malformed recurrence: {}", rec))
+        };
+        let amount = match cap.name("amount") {
+            Some(amount) => amount.as_str().parse::<u32>().map_err(|e| e.to_string())?,
+            None => 1
+        };
+        if amount == 0 {
+            return Err(format!("malformed recurrence: {}", rec));
+        }
+        let unit = match cap.name("unit").unwrap().as_str() {
+            "d" => RecurrenceUnit::Day,
+            "b" => RecurrenceUnit::BusinessDay,
+            "w" => RecurrenceUnit::Week,
+            "m" => RecurrenceUnit::Month,
+            _ => RecurrenceUnit::Year
+        };
+        Ok(Recurrence { strict : cap.name("strict").is_some(), amount, unit })
+    }
+
+    /// Shift a date by the recurrence interval
+    /// 
+    /// Returns `None` if the resulting date is out of range
+    /// 
+    /// Arguments:
+    /// 
+    /// * `date` - the date to shift
+    pub fn apply(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self.unit {
+            RecurrenceUnit::Day => date.checked_add_signed(Duration::days(self.amount as i64)),
+            RecurrenceUnit::Week => date.checked_add_signed(Duration::weeks(self.amount as i64)),
+            RecurrenceUnit::Month => date.checked_add_months(Months::new(self.amount)),
+            RecurrenceUnit::Year => date.checked_add_months(Months::new(self.amount.checked_mul(12)?)),
+            RecurrenceUnit::BusinessDay => {
+                let mut next = date;
+                let mut remaining = self.amount;
+                while remaining > 0 {
+                    next = next.succ_opt()?;
+                    if next.weekday() != Weekday::Sat && next.weekday() != Weekday::Sun {
+                        remaining -= 1;
+                    }
+                }
+                Some(next)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod recurrence_tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parse_recurrence() {
+        assert_eq!(Recurrence::parse("1w").unwrap(), Recurrence { strict : false, amount : 1, unit : RecurrenceUnit::Week });
+        assert_eq!(Recurrence::parse("+3d").unwrap(), Recurrence { strict : true, amount : 3, unit : RecurrenceUnit::Day });
+        assert_eq!(Recurrence::parse("2m").unwrap(), Recurrence { strict : false, amount : 2, unit : RecurrenceUnit::Month });
+        assert_eq!(Recurrence::parse("y").unwrap(), Recurrence { strict : false, amount : 1, unit : RecurrenceUnit::Year });
+        assert_eq!(Recurrence::parse("+5b").unwrap(), Recurrence { strict : true, amount : 5, unit : RecurrenceUnit::BusinessDay });
+    }
+
+    #[test]
+    fn parse_malformed_recurrence() {
+        assert!(Recurrence::parse("").is_err());
+        assert!(Recurrence::parse("0d").is_err());
+        assert!(Recurrence::parse("1x").is_err());
+        assert!(Recurrence::parse("-1w").is_err());
+        assert!(Recurrence::parse("1w2").is_err());
+    }
+
+    #[test]
+    fn apply_recurrence() {
+        assert_eq!(Recurrence::parse("3d").unwrap().apply(date("2021-12-30")), Some(date("2022-01-02")));
+        assert_eq!(Recurrence::parse("1w").unwrap().apply(date("2021-12-30")), Some(date("2022-01-06")));
+        assert_eq!(Recurrence::parse("1m").unwrap().apply(date("2021-01-31")), Some(date("2021-02-28")));
+        assert_eq!(Recurrence::parse("1y").unwrap().apply(date("2020-02-29")), Some(date("2021-02-28")));
+        // 2021-10-15 is a friday
+        assert_eq!(Recurrence::parse("1b").unwrap().apply(date("2021-10-15")), Some(date("2021-10-18")));
+        assert_eq!(Recurrence::parse("6b").unwrap().apply(date("2021-10-15")), Some(date("2021-10-25")));
+    }
+}
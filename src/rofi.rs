@@ -1,20 +1,139 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::process::Command;
-use std::process::Stdio;
-use std::io::Write;
+use crate::menu::{self, Backend, Highlight, Menu, Selection};
+use chrono::Weekday;
+use crate::i18n::{self, Lang};
 
 pub struct RofiParams {
     pub no_config: bool,
-    pub case_insensitive: bool
+    pub case_insensitive: bool,
+    /// Show the tasks with Pango markup
+    pub markup: bool,
+    /// The program used to show the menus
+    pub backend: Backend,
+    /// The language of the menus
+    pub lang: Lang,
+    /// The format of the dates shown in the menus
+    pub date_format: String,
+    /// A Rofi theme string applied to every menu
+    pub theme: Option<String>,
+    /// The texts replacing the messages of the language, by key
+    pub messages: HashMap<String, String>
+}
+
+impl Default for RofiParams {
+    fn default() -> Self {
+        RofiParams {
+            no_config : false,
+            case_insensitive : false,
+            markup : false,
+            backend : Backend::Rofi,
+            lang : Lang::En,
+            date_format : String::from("%Y-%m-%d"),
+            theme : None,
+            messages : HashMap::new()
+        }
+    }
+}
+
+impl RofiParams {
+    /// Are the tasks shown with Pango markup, only if the program renders it
+    pub fn use_markup(&self) -> bool {
+        self.markup && self.backend.renders_markup()
+    }
+
+    /// Get the text of a message in the language of the menus, unless it is replaced in the configuration file
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the message, such as `menu.add`
+    pub fn tr(&self, key: &'static str) -> &str {
+        match self.messages.get(key) {
+            Some(text) => text,
+            None => self.lang.tr(key)
+        }
+    }
+
+    /// Get the name of a month, like a message
+    /// 
+    /// Arguments:
+    /// 
+    /// * `month` - the month, from 1 to 12
+    pub fn month(&self, month: u32) -> &str {
+        self.tr(i18n::month_key(month))
+    }
+
+    /// Get the short name of a weekday, shown in the calendar, like a message
+    /// 
+    /// Arguments:
+    /// 
+    /// * `day` - the weekday
+    pub fn weekday(&self, day: Weekday) -> &str {
+        self.tr(i18n::weekday_key(day))
+    }
+
+    /// Get the name of a weekday, like a message
+    /// 
+    /// Arguments:
+    /// 
+    /// * `day` - the weekday
+    pub fn weekday_name(&self, day: Weekday) -> &str {
+        self.tr(i18n::weekday_name_key(day))
+    }
+}
+
+/// An error of the program showing a menu
+#[derive(Clone, Debug, PartialEq)]
+pub enum RofiError {
+    /// The program isn't installed
+    NotInstalled(String),
+    /// The user quitted the menu
+    Cancelled,
+    /// The program was killed by a signal
+    Killed,
+    /// The program exited with an unexpected code
+    Failed(i32),
+    /// The output of the program isn't valid UTF-8
+    NotUtf8,
+    /// A custom keybinding not bound by the menu was used, from the configuration of the program, the menu being shown again
+    CustomKey(usize),
+    /// The program couldn't be launched or read
+    Io(String)
+}
+
+impl fmt::Display for RofiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RofiError::NotInstalled(program) => write!(f, "{} is not installed, or not in the PATH", program),
+            RofiError::Cancelled => write!(f, "the menu was cancelled"),
+            RofiError::Killed => write!(f, "the menu was killed"),
+            RofiError::Failed(code) => write!(f, "the menu failed with the exit code {}", code),
+            RofiError::NotUtf8 => write!(f, "the menu returned an invalid UTF-8 text"),
+            RofiError::CustomKey(custom) => write!(f, "the custom keybinding {} isn't used by this menu", custom),
+            RofiError::Io(s) => write!(f, "failed to run the menu: {}", s)
+        }
+    }
 }
 
 pub struct Rofi {
     rofi: Command,
+    /// The custom keybindings bound by the menu
+    custom_keys: Vec<usize>,
+    /// The message shown under the prompt
+    msg: Option<String>,
+    /// Are the entries and the message Pango markup
+    markup: bool,
+    /// The ranges of active rows
+    active: Vec<(usize, usize)>,
+    /// The ranges of urgent rows
+    urgent: Vec<(usize, usize)>
 }
 
 impl Rofi {
     /// Create a new Rofi instance
     pub fn new() -> Self {
-        let mut r = Rofi { rofi : Command::new("rofi") };
+        let mut r = Rofi { rofi : Command::new("rofi"), custom_keys : Vec::new(), msg : None, markup : false, active : Vec::new(), urgent : Vec::new() };
         r.rofi.arg("-dmenu");
         r
     }
@@ -27,145 +146,157 @@ impl Rofi {
     pub fn from(p : &RofiParams) -> Self {
         let mut rofi = Self::new();
         if p.no_config {
-            rofi = rofi.no_config();
+            rofi.no_config();
         }
         if p.case_insensitive {
-            rofi = rofi.case_insensitive();
+            rofi.case_insensitive();
+        }
+        if let Some(theme) = &p.theme {
+            rofi.rofi.arg("-theme-str").arg(theme);
         }
         rofi
     }
 
-    /// Launch Rofi with a list of entries
-    /// 
-    /// Arguments:
+    /// Do not load the Rofi config
     /// 
-    /// * `entries` - a vector of `String` to display as options in Rofi
-    pub fn run(mut self, entries: Vec<String>) -> Result<String, String> {
-        let mut proc = self.rofi.stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()          
-            .ok()
-            .expect("failed to spawn rust");
-
-
-        let entry_list = vec2str(entries);
-        proc.stdin.as_mut().unwrap().write_all(entry_list.as_bytes()).expect("Erreur avec stdin");
+    /// Equivalent to `-no-config` Rofi flag
+    pub fn no_config(&mut self) {
+        self.rofi.arg("-no-config");
+    }
 
-        let mut retour = String::from_utf8(proc.wait_with_output().unwrap().stdout).unwrap();
-        trim_newline(&mut retour);
-        Ok(retour)
+    /// Make the filter case insensitive
+    pub fn case_insensitive(&mut self) {
+        self.rofi.arg("-i");
     }
+}
 
-    /// Print a message under the prompt
-    /// 
-    /// Pango markup is currently disabled because user tasks content is interpreted
-    /// 
-    /// Arguments:
-    /// 
-    /// * `m` - the `String` to display
-    pub fn msg(mut self, m: String) -> Self {
-        self.rofi.arg("-theme-str").arg("textbox { markup: false; }").arg("-mesg").arg(m);
-        self
+impl Menu for Rofi {
+    /// Rofi gives the index of the selected entry, with `-format`
+    fn show(&mut self, entries: Vec<String>) -> Result<Selection, RofiError> {
+        if let Some(m) = self.msg.take() {
+            // Pango markup is disabled unless enabled for the menu, because user tasks content would be interpreted
+            if !self.markup {
+                self.rofi.arg("-theme-str").arg("textbox { markup: false; }");
+            }
+            self.rofi.arg("-mesg").arg(m);
+        }
+        if !self.active.is_empty() {
+            self.rofi.arg("-a").arg(menu::rows_list(&self.active));
+        }
+        if !self.urgent.is_empty() {
+            self.rofi.arg("-u").arg(menu::rows_list(&self.urgent));
+        }
+        self.rofi.arg("-format").arg("i s");
+        loop {
+            let output = menu::launch(&mut self.rofi, &entries)?;
+            match rofi_code(menu::exit_code(output.status)?, &self.custom_keys) {
+                // The custom keybindings of the Rofi configuration not bound by the menu (Alt+1...) do nothing
+                Err(RofiError::CustomKey(_)) => continue,
+                code => return Ok(rofi_selection(&menu::output_text(output.stdout)?, code?))
+            }
+        }
     }
 
-    /// Do not load the Rofi config
-    /// 
-    /// Equivalent to `-no-config` Rofi flag
-    pub fn no_config(mut self) -> Self {
-        self.rofi.arg("-no-config");
+    fn msg(&mut self, m: String) -> &mut dyn Menu {
+        self.msg = Some(m);
         self
     }
 
-    /// Mark a range of index as active
-    /// 
     /// Use `-a` Rofi flag
-    pub fn select_range(mut self, start: usize, end: usize) -> Self {
-        self.rofi.arg("-a").arg(format!("{}-{}", start, end));
+    fn select_range(&mut self, start: usize, end: usize) -> &mut dyn Menu {
+        self.active.push((start, end));
         self
     }
 
-    /// Pre-enter an input text
-    /// 
-    /// Arguments:
-    /// 
-    /// * `text` - the `String` to input
-    pub fn pretext(mut self, text: String) -> Self {
-        self.rofi.arg("-filter").arg(text);
+    /// Use `-a` and `-u` Rofi flags
+    fn highlight(&mut self, rows: &[usize], highlight: Highlight) -> &mut dyn Menu {
+        let ranges = rows.iter().map(|row| (*row, *row));
+        match highlight {
+            Highlight::Active => self.active.extend(ranges),
+            Highlight::Urgent => self.urgent.extend(ranges)
+        }
         self
     }
 
-    /// Make the filter case insensitive
-    pub fn case_insensitive(mut self) -> Self {
-        self.rofi.arg("-i");
+    fn pretext(&mut self, text: String) -> &mut dyn Menu {
+        self.rofi.arg("-filter").arg(text);
         self
     }
 
-    /// Change the prompt
-    /// 
-    /// Arguments:
-    /// 
-    /// * `p` - the `String` to prompt
-    pub fn prompt(mut self, p: &str) -> Self {
+    fn prompt(&mut self, p: &str) -> &mut dyn Menu {
         self.rofi.arg("-p")
             .arg(p);
         self
     }
 
-    /// Select a specific line
-    /// 
-    /// Arguments:
-    /// 
-    /// * `index` - the index of the line
-    pub fn selected(mut self, index: u32) -> Self {
+    fn selected(&mut self, index: u32) -> &mut dyn Menu {
         self.rofi.arg("-selected-row")
             .arg(index.to_string());
         self
     }
 
-    /// The placeholder to put in the input zone
-    /// 
-    /// Arguments:
-    /// 
-    /// * `placeholder` - the placeholder to show
-    pub fn placeholder(mut self, placeholder: &str) -> Self {
+    fn placeholder(&mut self, placeholder: &str) -> &mut dyn Menu {
         self.rofi.arg("-theme-str")
             .arg(format!("entry {{ placeholder: \"{}\"; }}", placeholder));
         self
     }
 
-    /// Mask lines under the input
-    pub fn text_only(mut self) -> Self {
+    fn text_only(&mut self) -> &mut dyn Menu {
         self.rofi.arg("-l").arg("0");
         self
     }
+
+    /// Use `-markup-rows` Rofi flag, the message being rendered as markup too
+    fn markup(&mut self) -> &mut dyn Menu {
+        self.rofi.arg("-markup-rows");
+        self.markup = true;
+        self
+    }
+
+    fn grid(&mut self, columns: usize, rows: usize) -> &mut dyn Menu {
+        self.rofi.arg("-theme-str").arg(format!("listview {{ columns: {}; lines: {}; }}", columns, rows));
+        self
+    }
+
+    /// Use `-kb-custom-<custom>` Rofi option, Rofi exiting with the code `9 + custom`
+    fn custom_key(&mut self, custom: usize, key: &str) -> &mut dyn Menu {
+        self.rofi.arg(format!("-kb-custom-{}", custom)).arg(key);
+        self.custom_keys.push(custom);
+        self
+    }
 }
 
-/// Remove the trailing newlines of a String
+/// Check the exit code of Rofi
+/// 
+/// Rofi exits with 1 when the user quits, and with 10 to 28 for the custom keybindings 1 to 19
 /// 
 /// Arguments:
 /// 
-/// * `s` - the `String`
-fn trim_newline(s: &mut String) {
-    if s.ends_with('\n') {
-        s.pop();
-        if s.ends_with('\r') {
-            s.pop();
-        }
+/// * `code` - the exit code of Rofi
+/// * `custom_keys` - the custom keybindings bound by the menu
+fn rofi_code(code: i32, custom_keys: &[usize]) -> Result<i32, RofiError> {
+    match code {
+        0 => Ok(code),
+        1 => Err(RofiError::Cancelled),
+        10..=28 if custom_keys.contains(&(code as usize - 9)) => Ok(code),
+        10..=28 => Err(RofiError::CustomKey(code as usize - 9)),
+        _ => Err(RofiError::Failed(code))
     }
 }
 
-/// Transform a `Vec<String>` to a String with line breaks between each entry
+/// Get the selection from the output of Rofi with `-format "i s"`
+/// 
+/// The output is the index of the selected entry followed by its text, the index being `-1` for a typed text
 /// 
 /// Arguments:
 /// 
-/// * `tab` - the `Vec<String>` of entries
-fn vec2str(tab : Vec<String>) -> String {
-    let mut s = String::new();
-    for x in tab {
-        s.push_str(&x);
-        s.push('\n');
-    }
-    s
+/// * `output` - the output of Rofi
+/// * `code` - the exit code of Rofi
+fn rofi_selection(output: &str, code: i32) -> Selection {
+    match output.split_once(' ') {
+        Some((index, text)) => Selection { index : index.parse::<usize>().ok(), text : String::from(text), code },
+        None => Selection { index : None, text : String::new(), code }
+    }
 }
 
 #[cfg(test)]
@@ -173,22 +304,31 @@ mod rofiutils_tests {
     use super::*;
 
     #[test]
-    fn trim_newline_test() {
-        let mut test_string = String::from("Hello\n");
-        trim_newline(&mut test_string);
-        assert_eq!(test_string, "Hello");
-        let mut test_string2 = String::from("Hi!");
-        trim_newline(&mut test_string2);
-        assert_eq!(test_string2, "Hi!");
-        let mut test_string3 = String::from("foobar\r\n");
-        trim_newline(&mut test_string3);
-        assert_eq!(test_string3, "foobar");
+    fn rofi_arguments() {
+        let mut rofi = Rofi::from(&RofiParams { no_config : true, case_insensitive : true, ..RofiParams::default() });
+        rofi.prompt("Todo").selected(2).text_only().markup().custom_key(1, "Alt+d");
+        let args = rofi.rofi.get_args().map(|a| a.to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(args, vec!["-dmenu", "-no-config", "-i", "-p", "Todo", "-selected-row", "2", "-l", "0", "-markup-rows", "-kb-custom-1", "Alt+d"]);
+
+        let rofi = Rofi::from(&RofiParams { theme : Some(String::from("window { width: 40%; }")), ..RofiParams::default() });
+        let args = rofi.rofi.get_args().map(|a| a.to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(args, vec!["-dmenu", "-theme-str", "window { width: 40%; }"]);
     }
 
     #[test]
-    fn vec2str_test() {
-        let test_vec = vec![String::from("foo"), String::from("bar")];
-        assert_eq!(vec2str(test_vec), String::from("foo\nbar\n"));
+    fn rofi_output() {
+        assert_eq!(rofi_selection("2 call mom", 0), Selection { index : Some(2), text : String::from("call mom"), code : 0 });
+        assert_eq!(rofi_selection("-1 a new task", 0), Selection { index : None, text : String::from("a new task"), code : 0 });
+        assert_eq!(rofi_selection("0 ", 0), Selection { index : Some(0), text : String::new(), code : 0 });
+        assert_eq!(rofi_selection("", 1), Selection { index : None, text : String::new(), code : 1 });
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn rofi_exit_codes() {
+        assert_eq!(rofi_code(0, &[]), Ok(0));
+        assert_eq!(rofi_code(1, &[]), Err(RofiError::Cancelled));
+        assert_eq!(rofi_code(11, &[1, 2]), Ok(11));
+        assert_eq!(rofi_code(12, &[1, 2]), Err(RofiError::CustomKey(3)));
+        assert_eq!(rofi_code(65, &[]), Err(RofiError::Failed(65)));
+    }
+}
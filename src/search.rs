@@ -0,0 +1,141 @@
+use std::rc::Rc;
+use crate::task::Task;
+
+/// The score of a letter of the query found in the text
+const LETTER : i64 = 1;
+/// The bonus of a letter following the previous letter found
+const CONSECUTIVE : i64 = 4;
+/// The bonus of a letter starting a word of the text
+const WORD_START : i64 = 8;
+/// The bonus of a word of the query found as is in the text
+const EXACT : i64 = 10;
+
+/// Get the text searched in a task: its content, with its project and context tags, and its custom tags
+/// 
+/// Arguments:
+/// 
+/// * `task` - the task
+fn searched_text(task : &Task) -> Vec<char> {
+    let mut text = task.get_content().clone();
+    for (key, value) in task.get_custom_tags() {
+        text.push_str(&format!(" {}:{}", key, value));
+    }
+    text.to_lowercase().chars().collect()
+}
+
+/// Is a letter of a text the start of a word, such as the first letter of a tag after `+`, `@` or `:`
+/// 
+/// Arguments:
+/// 
+/// * `text` - the text
+/// * `pos` - the position of the letter
+fn word_start(text : &[char], pos : usize) -> bool {
+    pos == 0 || !text[pos - 1].is_alphanumeric()
+}
+
+/// Score how well a word of the query matches a text
+/// 
+/// The letters of the word must be found in order in the text, the letters found together
+/// and at the start of the words of the text scoring more.
+/// Returns `None` if the word doesn't match
+/// 
+/// Arguments:
+/// 
+/// * `word` - the word of the query, in lowercase
+/// * `text` - the text, in lowercase
+fn word_score(word : &[char], text : &[char]) -> Option<i64> {
+    if word.is_empty() {
+        return Some(0);
+    }
+    // A word found as is scores as if all its letters were found together
+    let exact = text.windows(word.len()).enumerate().filter(|(_, window)| *window == word).map(|(pos, _)| {
+        EXACT + LETTER * word.len() as i64 + CONSECUTIVE * (word.len() as i64 - 1) + if word_start(text, pos) { WORD_START } else { 0 }
+    }).max();
+    if exact.is_some() {
+        return exact;
+    }
+    let mut score = 0;
+    let mut letters = word.iter().peekable();
+    let mut previous = None;
+    for (pos, c) in text.iter().enumerate() {
+        if letters.peek() == Some(&c) {
+            letters.next();
+            score += LETTER;
+            if previous.is_some_and(|p| p + 1 == pos) {
+                score += CONSECUTIVE;
+            }
+            if word_start(text, pos) {
+                score += WORD_START;
+            }
+            previous = Some(pos);
+        }
+    }
+    if letters.peek().is_some() {
+        return None;
+    }
+    Some(score)
+}
+
+/// Score how well a query matches a task, every word of the query having to match
+/// 
+/// Returns `None` if the task doesn't match
+/// 
+/// Arguments:
+/// 
+/// * `query` - the query typed by the user
+/// * `task` - the task
+pub fn score(query : &str, task : &Task) -> Option<i64> {
+    let text = searched_text(task);
+    query.to_lowercase().split_whitespace().map(|word| word_score(&word.chars().collect::<Vec<_>>(), &text)).sum()
+}
+
+/// Find the tasks, open or done, matching a query
+/// 
+/// The best matches come first, the open tasks before the done ones for the same score
+/// 
+/// Arguments:
+/// 
+/// * `query` - the query typed by the user
+/// * `tasks` - the tasks
+pub fn search<'a>(query : &str, tasks : impl IntoIterator<Item = &'a Rc<Task>>) -> Vec<Rc<Task>> {
+    let mut found = tasks.into_iter().filter_map(|task| score(query, task).map(|score| (score, Rc::clone(task)))).collect::<Vec<_>>();
+    found.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(a.completion.cmp(&b.completion)).then(a.comp_content(b)));
+    found.into_iter().map(|(_, task)| task).collect()
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    fn task(line : &str) -> Rc<Task> {
+        Rc::new(Task::from_todotxt(String::from(line)).unwrap())
+    }
+
+    #[test]
+    fn fuzzy_scores() {
+        let report = task("(A) write report +work due:2026-10-20");
+        assert!(score("report", &report).is_some());
+        assert!(score("REPORT", &report).is_some());
+        assert!(score("wrt rpt", &report).is_some());
+        assert!(score("+work", &report).is_some());
+        assert!(score("due:2026-10", &report).is_some());
+        assert_eq!(score("report home", &report), None);
+        assert_eq!(score("tropre", &report), None);
+        // The priority and dates are not searched, only the content and tags
+        assert_eq!(score("(A)", &report), None);
+        // Exact words score more than scattered letters, word starts more than inner letters
+        assert!(score("report", &report) > score("rprt", &report));
+        assert!(score("rep", &report) > score("por", &report));
+    }
+
+    #[test]
+    fn ranked_results() {
+        let tasks = vec![task("repair the bike"), task("x 2026-10-01 send report"), task("write report"), task("call mom")];
+        let found = search("report", &tasks).into_iter().map(|t| t.get_content().clone()).collect::<Vec<_>>();
+        // Both tasks match as well, the open task coming first
+        assert_eq!(found, ["write report", "send report"]);
+        let found = search("rep", &tasks).into_iter().map(|t| t.get_content().clone()).collect::<Vec<_>>();
+        assert_eq!(found, ["repair the bike", "write report", "send report"]);
+        assert!(search("zzz", &tasks).is_empty());
+    }
+}
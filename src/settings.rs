@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::fmt;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use chrono::format::{Item, StrftimeItems};
+use crate::i18n::{self, Lang};
+use crate::keys::Keybindings;
+use crate::menu::Backend;
+
+/// The default values of the options, read from the configuration file
+/// 
+/// Every setting is optional, the options given on the command line taking precedence
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// The path to the task list file
+    pub todo_file : Option<PathBuf>,
+    /// The path to the archive of the completed tasks
+    pub done_file : Option<PathBuf>,
+    /// How to sort the tasks
+    pub sort : Option<String>,
+    /// The program used to show the menus
+    pub backend : Option<String>,
+    /// Do not load the Rofi configuration
+    pub no_config : Option<bool>,
+    /// Set the filter to be case insensitive
+    pub case_insensitive : Option<bool>,
+    /// Show the tasks with Pango markup
+    pub markup : Option<bool>,
+    /// The language of the menus
+    pub lang : Option<String>,
+    /// The format of the dates shown in the menus, such as `%d/%m/%Y`
+    pub date_format : Option<String>,
+    /// A Rofi theme string, such as `window { width: 40%; }`
+    pub theme : Option<String>,
+    /// The number of backups of the task list file
+    pub backups : Option<usize>,
+    /// Keep the history of the modifications in a journal
+    pub journal : Option<bool>,
+    /// The other task lists, with their name, in the order of the file
+    #[serde(default, deserialize_with = "ordered_lists")]
+    pub lists : Vec<(String, PathBuf)>,
+    /// The keys of the quick actions, by action
+    #[serde(default)]
+    pub keys : BTreeMap<String, String>,
+    /// The texts replacing the menu entries and prompts, by key of the message such as `menu.add`
+    #[serde(default)]
+    messages : BTreeMap<String, toml::Value>
+}
+
+/// Read the task lists of the configuration file, keeping their order, unlike a map
+fn ordered_lists<'de, D : Deserializer<'de>>(deserializer : D) -> Result<Vec<(String, PathBuf)>, D::Error> {
+    struct Lists;
+
+    impl<'de> Visitor<'de> for Lists {
+        type Value = Vec<(String, PathBuf)>;
+
+        fn expecting(&self, f : &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a table of task list paths, by name")
+        }
+
+        fn visit_map<A : MapAccess<'de>>(self, mut map : A) -> Result<Self::Value, A::Error> {
+            let mut lists = Vec::new();
+            while let Some(list) = map.next_entry()? {
+                lists.push(list);
+            }
+            Ok(lists)
+        }
+    }
+
+    deserializer.deserialize_map(Lists)
+}
+
+/// Get the path of the configuration file, `$XDG_CONFIG_HOME/rofitodo/config.toml`
+/// 
+/// `$XDG_CONFIG_HOME` defaults to `~/.config`
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => home::home_dir()?.join(".config")
+    };
+    Some(config_home.join("rofitodo").join("config.toml"))
+}
+
+/// Replace the `~` starting a path by the home directory
+/// 
+/// Arguments:
+/// 
+/// * `path` - the path
+pub fn expand_home(path : PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), home::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path
+    }
+}
+
+/// Check that a date format only contains valid specifiers
+/// 
+/// Arguments:
+/// 
+/// * `format` - the date format
+fn check_date_format(format : &str) -> Result<(), String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("invalid date format: {}", format));
+    }
+    Ok(())
+}
+
+/// Flatten the messages, a table such as `[messages.menu]` giving the keys starting with `menu.`
+/// 
+/// Arguments:
+/// 
+/// * `prefix` - the key of the table
+/// * `table` - the messages of the table
+/// * `messages` - the flattened messages
+fn flatten<'a>(prefix : &str, table : impl IntoIterator<Item = (&'a String, &'a toml::Value)>, messages : &mut HashMap<String, String>) -> Result<(), String> {
+    for (name, value) in table {
+        let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match value {
+            toml::Value::String(text) => {
+                if !i18n::is_message(&key) {
+                    return Err(format!("unknown message: {}", key));
+                }
+                messages.insert(key, text.clone());
+            },
+            toml::Value::Table(table) => flatten(&key, table, messages)?,
+            _ => return Err(format!("the message {} must be a text", key))
+        }
+    }
+    Ok(())
+}
+
+impl Settings {
+    /// Read the settings of a configuration file, the default settings if the file doesn't exist
+    /// 
+    /// Arguments:
+    /// 
+    /// * `path` - the path to the configuration file
+    pub fn load(path : &Path) -> Result<Self, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e))
+        };
+        Settings::parse(&text).map_err(|e| format!("Invalid configuration file {}: {}", path.display(), e))
+    }
+
+    /// Parse and check the settings of a configuration file
+    /// 
+    /// Arguments:
+    /// 
+    /// * `text` - the content of the configuration file, in TOML
+    pub fn parse(text : &str) -> Result<Self, String> {
+        let mut settings : Settings = toml::from_str(text).map_err(|e| e.to_string())?;
+        settings.todo_file = settings.todo_file.map(expand_home);
+        settings.done_file = settings.done_file.map(expand_home);
+        settings.lists = settings.lists.into_iter().map(|(name, path)| (name, expand_home(path))).collect();
+        if let Some(sort) = &settings.sort {
+            if !["content", "creation", "priority", "due"].contains(&sort.to_lowercase().as_ref()) {
+                return Err(format!("unknown sort order: {} (expected creation, content, priority or due)", sort));
+            }
+        }
+        if let Some(backend) = &settings.backend {
+            backend.parse::<Backend>()?;
+        }
+        if let Some(lang) = &settings.lang {
+            lang.parse::<Lang>()?;
+        }
+        if let Some(format) = &settings.date_format {
+            check_date_format(format)?;
+        }
+        settings.keybindings(Keybindings::default())?;
+        settings.messages()?;
+        Ok(settings)
+    }
+
+    /// Change the keys of the quick actions set in the configuration file
+    /// 
+    /// Arguments:
+    /// 
+    /// * `keys` - the keybindings to change
+    pub fn keybindings(&self, mut keys : Keybindings) -> Result<Keybindings, String> {
+        for (action, key) in &self.keys {
+            keys.set(&format!("{}={}", action, key))?;
+        }
+        Ok(keys)
+    }
+
+    /// Get the texts replacing the menu entries and prompts, by key of the message
+    pub fn messages(&self) -> Result<HashMap<String, String>, String> {
+        let mut messages = HashMap::new();
+        flatten("", &self.messages, &mut messages)?;
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod settings_tests {
+    use super::*;
+    use crate::rofi::RofiParams;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn parse_settings() {
+        let settings = Settings::parse(r#"
+            todo_file = "/home/user/todo.txt"
+            sort = "due"
+            backend = "fzf"
+            markup = true
+            lang = "fr"
+            date_format = "%d/%m/%Y"
+            theme = "window { width: 40%; }"
+
+            [lists]
+            work = "~/work.txt"
+            home = "/home/user/home.txt"
+
+            [keys]
+            complete = "Alt+c"
+            edit = ""
+
+            [messages]
+            menu.add = "➕ add"
+            "task.remove" = "🗑 remove"
+        "#).unwrap();
+        assert_eq!(settings.todo_file, Some(PathBuf::from("/home/user/todo.txt")));
+        assert_eq!(settings.sort.as_deref(), Some("due"));
+        assert_eq!(settings.markup, Some(true));
+        assert_eq!(settings.journal, None);
+        assert_eq!(settings.theme.as_deref(), Some("window { width: 40%; }"));
+        // The lists keep the order of the file
+        assert_eq!(settings.lists, vec![(String::from("work"), home::home_dir().unwrap().join("work.txt")), (String::from("home"), PathBuf::from("/home/user/home.txt"))]);
+
+        let keys = settings.keybindings(Keybindings::default()).unwrap();
+        assert_eq!(keys.help(&RofiParams::default()), "Alt+c : mark as done   Alt+p : change priority   Alt+r : remove");
+
+        let messages = settings.messages().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages["menu.add"], "➕ add");
+        assert_eq!(messages["task.remove"], "🗑 remove");
+
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+        let home = home::home_dir().unwrap();
+        assert_eq!(Settings::parse("done_file = \"~/done.txt\"").unwrap().done_file, Some(home.join("done.txt")));
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let error = Settings::parse("sorting = \"due\"").unwrap_err();
+        assert!(error.starts_with("unknown field `sorting`"), "{}", error);
+        assert!(Settings::parse("backups = \"three\"").is_err());
+        assert_eq!(Settings::parse("sort = \"size\""), Err(String::from("unknown sort order: size (expected creation, content, priority or due)")));
+        assert_eq!(Settings::parse("backend = \"xmenu\""), Err(String::from("unknown backend: xmenu")));
+        assert_eq!(Settings::parse("lang = \"de\""), Err(String::from("unsupported language: de (expected en or fr)")));
+        assert_eq!(Settings::parse("date_format = \"%d/%Q\""), Err(String::from("invalid date format: %d/%Q")));
+        assert!(Settings::parse("[keys]\narchive = \"Alt+a\"").unwrap_err().starts_with("unknown action: archive"));
+        assert_eq!(Settings::parse("[messages]\nmenu.clear = \"clear\""), Err(String::from("unknown message: menu.clear")));
+        assert_eq!(Settings::parse("[messages]\nmenu.add = 1"), Err(String::from("the message menu.add must be a text")));
+    }
+
+    #[test]
+    fn missing_file() {
+        let dir = TestDir::new("no_settings");
+        assert_eq!(Settings::load(&dir.join("config.toml")), Ok(Settings::default()));
+    }
+}
@@ -1,7 +1,9 @@
-use chrono::{NaiveDate, Local, Datelike};
+use chrono::{NaiveDate, Local};
 use regex::{Regex, CaptureMatches, Captures};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::recurrence::Recurrence;
 
 
 #[derive(Clone)]
@@ -12,13 +14,20 @@ pub enum SortTaskBy {
     DueDate
 }
 
+/// The identifier given to the next created task
+static NEXT_TASK_ID : AtomicUsize = AtomicUsize::new(0);
+
 /// A task struct
-#[derive(Clone,Debug)]
+#[derive(Debug)]
 pub struct Task {
+    /// An identifier unique to each task created during the session, kept when the task is edited
+    id : usize,
     /// The content of the task
     pub content : String,
     /// An optionnal `NaiveDate` corresponding to when the task should be done
     duedate : Option<NaiveDate>,
+    /// An optionnal `NaiveDate` before which the task can't be started
+    threshold : Option<NaiveDate>,
     /// Is the task done
     pub completion : bool,
     /// When the task was completed
@@ -31,8 +40,8 @@ pub struct Task {
     project_tags : Vec<String>,
     /// A list of context tags
     context_tags : Vec<String>,
-    /// Custom tags with key and value
-    custom_tags : HashMap<String,String>
+    /// Custom tags with key and value, in their order of appearance
+    custom_tags : Vec<(String,String)>
 }
 
 impl Task {
@@ -45,15 +54,17 @@ impl Task {
     /// * `content` - the content of the task
     pub fn empty() -> Self {
         Task {
+            id: NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed),
             content: String::new(),
             duedate: None,
+            threshold: None,
             completion : false,
             context_tags : vec![],
             project_tags : vec![],
             priority : None,
             creation_date : None,
             completion_date : None,
-            custom_tags : HashMap::new()
+            custom_tags : vec![]
         }
     }
 
@@ -65,10 +76,9 @@ impl Task {
     /// 
     /// * `content` - the content of the task
     pub fn new(content: String) -> Self {
-        let today = Local::now();
         let mut task = Self::empty();
         task.set_content(content);
-        task.creation_date = Some(NaiveDate::from_ymd(today.year(), today.month(), today.day()));
+        task.creation_date = Some(Local::now().date_naive());
         task
     }
 
@@ -86,6 +96,22 @@ impl Task {
         t
     }
 
+    /// Get the identifier of the task
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    /// Replace a task by another one, keeping its identity
+    /// 
+    /// Arguments:
+    /// 
+    /// * `other` - the task taking the place of this one
+    pub fn replace_with(&mut self, other: Task) {
+        let id = self.id;
+        *self = other;
+        self.id = id;
+    }
+
     /// Change the content of a task
     /// 
     /// Change the content of the task and extract the new tags
@@ -115,6 +141,11 @@ impl Task {
         &self.project_tags
     }
 
+    /// Return a reference to the custom tags, as key and value
+    pub fn get_custom_tags(&self) -> &Vec<(String,String)> {
+        &self.custom_tags
+    }
+
     /// Get the due date of the task
     pub fn get_due(&self) -> &Option<NaiveDate> {
         &self.duedate
@@ -130,8 +161,52 @@ impl Task {
     pub fn set_due(&mut self, date: Option<NaiveDate>) {
         self.duedate = date;
         match date {
-            Some(date) => { self.custom_tags.insert(String::from("due"), format!("{}",date.format("%Y-%m-%d"))); },
-            None => { self.custom_tags.remove_entry(&String::from("due")); }
+            Some(date) => self.set_tag("due", format!("{}",date.format("%Y-%m-%d"))),
+            None => self.remove_tag("due")
+        }
+    }
+
+    /// Get the threshold date of the task
+    pub fn get_threshold(&self) -> &Option<NaiveDate> {
+        &self.threshold
+    }
+
+    /// Set the threshold date of a task
+    /// 
+    /// Change the threshold date of the task and store it in a custom tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `date` - a `Option<NaiveDate>` containing the date or None
+    pub fn set_threshold(&mut self, date: Option<NaiveDate>) {
+        self.threshold = date;
+        match date {
+            Some(date) => self.set_tag("t", format!("{}",date.format("%Y-%m-%d"))),
+            None => self.remove_tag("t")
+        }
+    }
+
+    /// Return `true` if the task can't be started yet because its threshold date is in the future
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    pub fn is_upcoming(&self, today: NaiveDate) -> bool {
+        match self.threshold {
+            Some(date) => date > today,
+            None => false
+        }
+    }
+
+    /// Return `true` if the task isn't done and its due date is past
+    /// 
+    /// Arguments:
+    /// 
+    /// * `today` - the current date
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        match self.duedate {
+            Some(date) => !self.completion && date < today,
+            None => false
         }
     }
 
@@ -141,15 +216,82 @@ impl Task {
     /// If there is no creation date for the task, it creates a creation date identical to the completion date
     pub fn set_completed(&mut self) {
         self.completion = true;
-        let today = Local::now();
-        self.completion_date = Some(NaiveDate::from_ymd(today.year(), today.month(), today.day()));
+        let today = Local::now().date_naive();
+        self.completion_date = Some(today);
         // Adding a creation date to respect the todo.txt specification (no task with a completion date and without a creation date)
-        match self.creation_date {
-            None => self.creation_date = Some(NaiveDate::from_ymd(today.year(), today.month(), today.day())),
-            _ => ()
+        if self.creation_date.is_none() {
+            self.creation_date = Some(today);
+        }
+    }
+
+    /// Get the recurrence rule of the task from its `rec:` custom tag
+    pub fn get_recurrence(&self) -> Option<Recurrence> {
+        match self.get_tag("rec") {
+            Some(rec) => Recurrence::parse(rec).ok(),
+            None => None
+        }
+    }
+
+    /// Create the next occurrence of a recurring task
+    /// 
+    /// The due date (and the threshold date `t:` if any) are shifted by the recurrence interval,
+    /// from the previous due date for strict recurrences (`rec:+1w`) or from the completion date otherwise (`rec:1w`).
+    /// Returns `None` if the task has no valid `rec:` tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `completion_date` - the date when the current occurrence was completed
+    pub fn next_occurrence(&self, completion_date: NaiveDate) -> Option<Task> {
+        let rec = self.get_recurrence()?;
+        let base = if rec.strict { self.duedate.or(self.threshold).unwrap_or(completion_date) } else { completion_date };
+        let mut next = self.clone();
+        next.set_not_completed();
+        next.creation_date = Some(completion_date);
+        match (self.duedate, self.threshold) {
+            (Some(due), Some(t)) => {
+                let next_due = rec.apply(base)?;
+                next.set_due(Some(next_due));
+                next.set_threshold(Some(next_due - (due - t)));
+            },
+            (None, Some(_)) => next.set_threshold(Some(rec.apply(base)?)),
+            (_, None) => next.set_due(Some(rec.apply(base)?))
+        }
+        Some(next)
+    }
+
+    /// Get the value of a custom tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the tag
+    fn get_tag(&self, key: &str) -> Option<&String> {
+        self.custom_tags.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Set the value of a custom tag
+    /// 
+    /// An existing tag keeps its position, a new tag is added after the others
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the tag
+    /// * `value` - the new value of the tag
+    fn set_tag(&mut self, key: &str, value: String) {
+        match self.custom_tags.iter_mut().find(|(k, _)| k == key) {
+            Some(tag) => tag.1 = value,
+            None => self.custom_tags.push((String::from(key), value))
         }
     }
 
+    /// Remove a custom tag
+    /// 
+    /// Arguments:
+    /// 
+    /// * `key` - the key of the tag
+    fn remove_tag(&mut self, key: &str) {
+        self.custom_tags.retain(|(k, _)| k != key);
+    }
+
     /// Set a task as to do
     /// 
     /// Change the completion status to `false` and remove the completion date
@@ -158,53 +300,67 @@ impl Task {
         self.completion_date = None;
     }
 
-    /// Return a `String` representation of the task
+    /// Show the task in a list, with its priority and its due date
+    /// 
+    /// Arguments:
     /// 
-    /// Show the priority (optionnal), content and due date (optionnal)
-    pub fn to_string(&self) -> String  {
+    /// * `date_format` - the format of the due date
+    pub fn row_str(&self, date_format: &str) -> String {
         let mut s = String::new();
         if let Some(priority) = self.priority {
             s.push_str(&format!("({}) ", priority));
         }
         if let Some(date) = self.duedate {
-            s.push_str(&format!("{} : ", date.format("%Y-%m-%d")));
+            s.push_str(&format!("{} : ", date.format(date_format)));
         }
         s.push_str(&self.content);
         s
     }
 
     /// Show a complete description of the task
-    pub fn recap_str(&self) -> String {
+    /// 
+    /// Arguments:
+    /// 
+    /// * `tr` - a function giving the text of a label from its key, such as `recap.task`
+    /// * `date_format` - the format of the dates
+    pub fn recap_str<'a>(&self, tr: impl Fn(&'static str) -> &'a str, date_format: &str) -> String {
+        let label = |key| bold(tr(key));
         let mut s = String::new();
-        s.push_str(&format!("𝐓𝐚𝐬𝐤 : {}", self.get_content()));
+        s.push_str(&format!("{} : {}", label("recap.task"), self.get_content()));
         if self.completion {
-            s.push_str("\n𝐒𝐭𝐚𝐭𝐮𝐬 : Done");
+            s.push_str(&format!("\n{} : {}", label("recap.status"), tr("recap.done")));
             if let Some(date) = self.completion_date {
-                s.push_str(&format!(" ({})",date.format("%Y-%m-%d")));
+                s.push_str(&format!(" ({})",date.format(date_format)));
             }
         } else {
-            s.push_str("\n𝐒𝐭𝐚𝐭𝐮𝐬 : To do");
+            s.push_str(&format!("\n{} : {}", label("recap.status"), tr("recap.todo")));
         }
         if let Some(p) = self.priority {
-            s.push_str(&format!("\n𝐏𝐫𝐢𝐨𝐫𝐢𝐭𝐲 : {}", p));
+            s.push_str(&format!("\n{} : {}", label("recap.priority"), p));
         }
         if let Some(date) = self.creation_date {
-            s.push_str(&format!("\n𝐂𝐫𝐞𝐚𝐭𝐞𝐝 𝐨𝐧 : {}", date.format("%Y-%m-%d")));
+            s.push_str(&format!("\n{} : {}", label("recap.created"), date.format(date_format)));
         }
         if let Some(date) = self.duedate {
-            s.push_str(&format!("\n𝐃𝐮𝐞 𝐝𝐚𝐭𝐞 : {}", date.format("%Y-%m-%d")));
+            s.push_str(&format!("\n{} : {}", label("recap.due"), date.format(date_format)));
         }
-        if self.context_tags.len() > 0 {
-            s.push_str(&format!{"\n𝐂𝐨𝐧𝐭𝐞𝐱𝐭 𝐭𝐚𝐠𝐬 : {}", self.get_context_tags().join(", ")});
+        if let Some(date) = self.threshold {
+            s.push_str(&format!("\n{} : {}", label("recap.threshold"), date.format(date_format)));
         }
-        if self.project_tags.len() > 0 {
-            s.push_str(&format!{"\n𝐏𝐫𝐨𝐣𝐞𝐜𝐭 𝐭𝐚𝐠𝐬 : {}", self.get_project_tags().join(", ")});
+        if !self.context_tags.is_empty() {
+            s.push_str(&format!{"\n{} : {}", label("recap.contexts"), self.get_context_tags().join(", ")});
+        }
+        if !self.project_tags.is_empty() {
+            s.push_str(&format!{"\n{} : {}", label("recap.projects"), self.get_project_tags().join(", ")});
         }
         s
     }
 
     /// Import a `String` containing a todo.txt representation of a task and return a new `Task`
     /// 
+    /// Blank lines and lines with invalid dates are rejected.
+    /// For any accepted line, `to_todotxt` gives back the exact same line
+    /// 
     /// Arguments:
     /// 
     /// * `todo` - a `String` with a task following todo.txt format
@@ -212,13 +368,16 @@ impl Task {
         lazy_static! {
             static ref RE_TASK : Regex = Regex::new(r"^(?P<completion>x )?(\((?P<priority>[A-Z])\) )?(?P<compdate>\d{4}-\d{2}-\d{2} )?(?P<creadate>\d{4}-\d{2}-\d{2} )?(?P<content>.*)$").unwrap();
         }
-        let cap : Captures;
+
+        if todo.trim().is_empty() {
+            return Err(String::from("empty task"));
+        }
         // Check if the String respects the todo.txt standard
-        match RE_TASK.captures(&todo) {
+        let cap : Captures = match RE_TASK.captures(&todo) {
             None => return Err(String::from("malformed task")),
-            Some(result) => cap = result
-        }
-
+            Some(result) => result
+        };
+        let parse_date = |date: regex::Match| NaiveDate::parse_from_str(date.as_str(), "%Y-%m-%d ").map_err(|_| String::from("malformed date"));
 
         let mut task = Self::new(String::new());
         match cap.name("completion") {
@@ -234,10 +393,10 @@ impl Task {
         // If there are two date, it is a completion date then a creation date
         match cap.name("creadate") {
             Some(creadate) => {
-                task.creation_date = Some(NaiveDate::parse_from_str(&creadate.as_str(), "%Y-%m-%d ").unwrap());
+                task.creation_date = Some(parse_date(creadate)?);
                 match cap.name("compdate") {
                     Some(compdate) => {
-                        task.completion_date = Some(NaiveDate::parse_from_str(&compdate.as_str(), "%Y-%m-%d ").unwrap());
+                        task.completion_date = Some(parse_date(compdate)?);
                     },
                     None => task.completion_date = None
                 }
@@ -245,7 +404,7 @@ impl Task {
             None => {
                 match cap.name("compdate") {
                     Some(compdate) => {
-                        task.creation_date = Some(NaiveDate::parse_from_str(&compdate.as_str(), "%Y-%m-%d ").unwrap());
+                        task.creation_date = Some(parse_date(compdate)?);
                     },
                     None => {
                         task.completion_date = None;
@@ -261,7 +420,7 @@ impl Task {
             static ref RE_ALLTAGS : Regex = Regex::new(r"( ([^:\s]+):([^:\s]+))+$").unwrap();
             static ref RE_TAG : Regex = Regex::new(r"(?P<key>[^:\s]+):(?P<value>[^:\s]+)").unwrap();
         }
-        let alltags_result = RE_ALLTAGS.captures(&content);
+        let alltags_result = RE_ALLTAGS.captures(content);
         match alltags_result {
             None => task.content = String::from(content),
             Some(alltags) => {
@@ -269,7 +428,7 @@ impl Task {
                 task.content = String::from(&RE_ALLTAGS.replace_all(content, "").into_owned());
                 // Iterate over all found tags
                 for tag in RE_TAG.captures_iter(&alltags[0]) {
-                    task.custom_tags.insert(String::from(tag.name("key").unwrap().as_str()), String::from(tag.name("value").unwrap().as_str()));
+                    task.custom_tags.push((String::from(tag.name("key").unwrap().as_str()), String::from(tag.name("value").unwrap().as_str())));
                 }
             }
         }
@@ -278,12 +437,12 @@ impl Task {
         task.extract_tags();
 
         // Extract the due date from custom tags
-        match task.custom_tags.get(&String::from("due")) {
-            Some(str_date) => task.duedate = match NaiveDate::parse_from_str(str_date.as_str(), "%Y-%m-%d ") {
-                Ok(date) => Some(date),
-                Err(_) => None
-            },
-            None => ()
+        if let Some(str_date) = task.get_tag("due") {
+            task.duedate = NaiveDate::parse_from_str(str_date.as_str(), "%Y-%m-%d ").ok();
+        }
+        // Extract the threshold date from custom tags
+        if let Some(str_date) = task.get_tag("t") {
+            task.threshold = NaiveDate::parse_from_str(str_date.as_str(), "%Y-%m-%d ").ok();
         }
         Ok(task)
     }
@@ -339,7 +498,7 @@ impl Task {
     /// 
     /// * `compare` - a task to compare
     /// * `sort` - sort order
-    pub fn _comp(&self, compare: &Self, sort: &SortTaskBy) -> std::cmp::Ordering {
+    pub fn comp(&self, compare: &Self, sort: &SortTaskBy) -> std::cmp::Ordering {
         match sort {
             SortTaskBy::Content => {self.comp_content(compare)},
             SortTaskBy::CreationDate => {self.comp_creation_date(compare)},
@@ -390,6 +549,19 @@ impl Task {
         }
     }
 
+    /// Compare two `Task`s to sort them by threshold date
+    /// 
+    /// Arguments:
+    /// 
+    /// * `compare` - a task to compare
+    pub fn comp_threshold_date(&self, compare: &Self) -> std::cmp::Ordering {
+        match (self.threshold, compare.threshold) {
+            (Some(d1), Some(d2)) => if d1 == d2 {self.comp_due_date(compare)} else if d1 < d2 {std::cmp::Ordering::Less} else {std::cmp::Ordering::Greater},
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => self.comp_due_date(compare)
+        }
+    }
 
     // Compare two `Task`s to sort them by content
     /// 
@@ -407,31 +579,115 @@ impl Task {
     }
 }
 
+/// A `String` representation of the task
+///
+/// Show the priority (optionnal), content and due date (optionnal)
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.row_str("%Y-%m-%d"))
+    }
+}
+
+/// Cloning a task creates another task, with its own identifier
+impl Clone for Task {
+    fn clone(&self) -> Self {
+        Task {
+            id: NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed),
+            content: self.content.clone(),
+            duedate: self.duedate,
+            threshold: self.threshold,
+            completion: self.completion,
+            completion_date: self.completion_date,
+            creation_date: self.creation_date,
+            priority: self.priority,
+            project_tags: self.project_tags.clone(),
+            context_tags: self.context_tags.clone(),
+            custom_tags: self.custom_tags.clone()
+        }
+    }
+}
+
 impl Ord for Task {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.comp_content(other)
+        // Tasks sharing the same content are told apart by their identifier
+        self.comp_content(other).then(self.id.cmp(&other.id))
     }
 }
 
 impl PartialOrd for Task {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.comp_content(other))
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for Task {
     fn eq(&self, other: &Self) -> bool {
-        self.comp_content(other) == std::cmp::Ordering::Equal
+        self.cmp(other) == std::cmp::Ordering::Equal
     }
 }
 
 impl Eq for Task { }
 
+/// The letters with a diacritic, with their base letter and the combining diacritic written after it in bold
+const DIACRITICS : &[(char, char, char)] = &[
+    ('à', 'a', '\u{300}'), ('á', 'a', '\u{301}'), ('â', 'a', '\u{302}'), ('ä', 'a', '\u{308}'),
+    ('ç', 'c', '\u{327}'),
+    ('è', 'e', '\u{300}'), ('é', 'e', '\u{301}'), ('ê', 'e', '\u{302}'), ('ë', 'e', '\u{308}'),
+    ('í', 'i', '\u{301}'), ('î', 'i', '\u{302}'), ('ï', 'i', '\u{308}'),
+    ('ñ', 'n', '\u{303}'),
+    ('ó', 'o', '\u{301}'), ('ô', 'o', '\u{302}'), ('ö', 'o', '\u{308}'),
+    ('ù', 'u', '\u{300}'), ('ú', 'u', '\u{301}'), ('û', 'u', '\u{302}'), ('ü', 'u', '\u{308}'),
+    ('ÿ', 'y', '\u{308}')
+];
+
+/// Get the Unicode mathematical bold form of an ASCII letter or digit
+/// 
+/// Arguments:
+/// 
+/// * `c` - the character
+fn bold_char(c: char) -> Option<char> {
+    let bold = match c {
+        'A'..='Z' => 0x1D400 + (c as u32 - 'A' as u32),
+        'a'..='z' => 0x1D41A + (c as u32 - 'a' as u32),
+        '0'..='9' => 0x1D7CE + (c as u32 - '0' as u32),
+        _ => return None
+    };
+    char::from_u32(bold)
+}
 
+/// Write a text in bold with the Unicode mathematical bold letters and digits, the other characters being kept as is
+/// 
+/// The letters with a diacritic are written as their bold base letter followed by the diacritic.
+/// A text with another letter, which has no bold form, is kept as is rather than shown half in bold
+/// 
+/// Arguments:
+/// 
+/// * `text` - the text
+pub fn bold(text: &str) -> String {
+    let mut s = String::with_capacity(text.len() * 4);
+    for c in text.chars() {
+        if let Some(b) = bold_char(c) {
+            s.push(b);
+            continue;
+        }
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        match DIACRITICS.iter().find(|(letter, _, _)| *letter == lower) {
+            Some((_, base, diacritic)) => {
+                let base = if c.is_uppercase() { base.to_ascii_uppercase() } else { *base };
+                s.extend(bold_char(base));
+                s.push(*diacritic);
+            },
+            None if c.is_alphanumeric() => return text.to_string(),
+            None => s.push(c)
+        }
+    }
+    s
+}
 
 #[cfg(test)]
 mod task_tests {
     use super::*;
+    use crate::i18n::Lang;
     #[test]
     fn comp_date_nodate() {
         let t1 = Task::from_todotxt(String::from("a task")).unwrap();
@@ -472,28 +728,28 @@ mod task_tests {
     fn completed() {
         let mut t1 = Task::from_todotxt(String::from("a task")).unwrap();
         t1.set_completed();
-        assert_eq!(t1.completion, true);
+        assert!(t1.completion);
         assert_eq!(t1.creation_date, t1.completion_date);
 
         let mut t2 = Task::from_todotxt(String::from("2020-01-01 a task")).unwrap();
         t2.set_completed();
-        assert_eq!(t2.completion, true);
+        assert!(t2.completion);
         assert_ne!(t2.creation_date, t2.completion_date);
 
         let t3 = Task::from_todotxt(String::from("x a task")).unwrap();
-        assert_eq!(t3.completion, true);
+        assert!(t3.completion);
     }
 
     #[test]
     fn not_completed() {
         let t1 = Task::from_todotxt(String::from("a task")).unwrap();
-        assert_eq!(t1.completion, false);
+        assert!(!t1.completion);
 
         let mut t2 = Task::from_todotxt(String::from("2020-01-01 a task")).unwrap();
         t2.set_completed();
-        assert_eq!(t2.completion, true);
+        assert!(t2.completion);
         t2.set_not_completed();
-        assert_eq!(t2.completion, false);
+        assert!(!t2.completion);
         assert_eq!(t2.completion_date, None);
     }
 
@@ -503,7 +759,7 @@ mod task_tests {
         assert_eq!(t1.get_content(), "Thank Mom for the aaa @phone");
         assert_eq!(t1.creation_date, None);
         assert_eq!(t1.completion_date, None);
-        assert_eq!(t1.completion, false);
+        assert!(!t1.completion);
         assert_eq!(t1.priority, Some('A'));
         assert_eq!(*t1.get_context_tags(), vec!["phone"]);
         assert_eq!(*t1.get_project_tags(), Vec::<String>::new());
@@ -512,7 +768,7 @@ mod task_tests {
         assert_eq!(t2.get_content(), "Schedule Goodwill pickup +GarageSale @phone");
         assert_eq!(t2.creation_date, None);
         assert_eq!(t2.completion_date, None);
-        assert_eq!(t2.completion, false);
+        assert!(!t2.completion);
         assert_eq!(t2.priority, Some('B'));
         assert_eq!(*t2.get_context_tags(), vec!["phone"]);
         assert_eq!(*t2.get_project_tags(), vec!["GarageSale"]);
@@ -521,7 +777,7 @@ mod task_tests {
         assert_eq!(t3.get_content(), "Post signs around the neighborhood +GarageSale");
         assert_eq!(t3.creation_date, None);
         assert_eq!(t3.completion_date, None);
-        assert_eq!(t3.completion, true);
+        assert!(t3.completion);
         assert_eq!(t3.priority, None);
         assert_eq!(*t3.get_context_tags(), Vec::<String>::new());
         assert_eq!(*t3.get_project_tags(), vec!["GarageSale"]);
@@ -530,9 +786,121 @@ mod task_tests {
         assert_eq!(t4.get_content(), "@GroceryStore Eskimo pies");
         assert_eq!(format!("{}", t4.creation_date.unwrap().format("%Y-%m-%d")), "2021-09-01");
         assert_eq!(t4.completion_date, None);
-        assert_eq!(t4.completion, false);
+        assert!(!t4.completion);
         assert_eq!(t4.priority, None);
         assert_eq!(*t4.get_context_tags(), vec!["GroceryStore"]);
         assert_eq!(*t4.get_project_tags(), Vec::<String>::new());
     }
+
+    #[test]
+    fn recurrence_from_completion() {
+        let completion = NaiveDate::from_ymd_opt(2021, 10, 20).unwrap();
+        let t1 = Task::from_todotxt(String::from("(A) 2021-10-01 water plants @home due:2021-10-10 rec:1w")).unwrap();
+        let next = t1.next_occurrence(completion).unwrap();
+        assert!(!next.completion);
+        assert_eq!(next.priority, Some('A'));
+        assert_eq!(next.get_content(), "water plants @home");
+        assert_eq!(next.creation_date, Some(completion));
+        assert_eq!(*next.get_due(), NaiveDate::from_ymd_opt(2021, 10, 27));
+
+        let t2 = Task::from_todotxt(String::from("x 2021-10-20 2021-10-01 pay rent rec:1m")).unwrap();
+        let next = t2.next_occurrence(completion).unwrap();
+        assert!(!next.completion);
+        assert_eq!(next.completion_date, None);
+        assert_eq!(*next.get_due(), NaiveDate::from_ymd_opt(2021, 11, 20));
+
+        let t3 = Task::from_todotxt(String::from("not recurring due:2021-10-10")).unwrap();
+        assert!(t3.next_occurrence(completion).is_none());
+    }
+
+    #[test]
+    fn recurrence_strict() {
+        let completion = NaiveDate::from_ymd_opt(2021, 10, 20).unwrap();
+        let t1 = Task::from_todotxt(String::from("take out trash due:2021-10-10 rec:+3d")).unwrap();
+        let next = t1.next_occurrence(completion).unwrap();
+        assert_eq!(*next.get_due(), NaiveDate::from_ymd_opt(2021, 10, 13));
+
+        // The threshold date keeps the same offset to the due date
+        let t2 = Task::from_todotxt(String::from("file taxes t:2021-04-01 due:2021-04-15 rec:+1y")).unwrap();
+        let next = t2.next_occurrence(completion).unwrap();
+        assert_eq!(*next.get_due(), NaiveDate::from_ymd_opt(2022, 4, 15));
+        assert_eq!(*next.get_threshold(), NaiveDate::from_ymd_opt(2022, 4, 1));
+        assert!(next.to_todotxt().contains("t:2022-04-01"));
+    }
+
+    #[test]
+    fn occurrences_are_different_tasks() {
+        let mut t1 = Task::from_todotxt(String::from("water plants due:2021-10-10 rec:1w")).unwrap();
+        t1.set_completed();
+        let next = t1.next_occurrence(NaiveDate::from_ymd_opt(2021, 10, 20).unwrap()).unwrap();
+        assert_ne!(t1, next);
+    }
+
+    #[test]
+    fn threshold() {
+        let today = NaiveDate::from_ymd_opt(2021, 10, 20).unwrap();
+        let mut t1 = Task::from_todotxt(String::from("renew passport t:2021-11-01")).unwrap();
+        assert_eq!(*t1.get_threshold(), NaiveDate::from_ymd_opt(2021, 11, 1));
+        assert!(t1.is_upcoming(today));
+        assert!(!t1.is_upcoming(NaiveDate::from_ymd_opt(2021, 11, 1).unwrap()));
+
+        t1.set_threshold(None);
+        assert!(!t1.is_upcoming(today));
+        assert!(!t1.to_todotxt().contains("t:"));
+
+        t1.set_threshold(NaiveDate::from_ymd_opt(2021, 10, 25));
+        assert!(t1.is_upcoming(today));
+        assert!(t1.to_todotxt().contains("t:2021-10-25"));
+
+        let t2 = Task::from_todotxt(String::from("malformed threshold t:soon")).unwrap();
+        assert_eq!(*t2.get_threshold(), None);
+        assert!(!t2.is_upcoming(today));
+    }
+
+    #[test]
+    fn overdue() {
+        let today = NaiveDate::from_ymd_opt(2021, 10, 20).unwrap();
+        let mut t1 = Task::from_todotxt(String::from("pay rent due:2021-10-19")).unwrap();
+        assert!(t1.is_overdue(today));
+        assert!(!t1.is_overdue(NaiveDate::from_ymd_opt(2021, 10, 19).unwrap()));
+        t1.set_completed();
+        assert!(!t1.is_overdue(today));
+        assert!(!Task::from_todotxt(String::from("no date")).unwrap().is_overdue(today));
+    }
+
+    #[test]
+    fn identity() {
+        let t1 = Task::from_todotxt(String::from("call mom")).unwrap();
+        let t2 = Task::from_todotxt(String::from("call mom")).unwrap();
+        assert_ne!(t1.id, t2.id);
+        assert_ne!(t1, t2);
+        assert_eq!(t1.comp_content(&t2), std::cmp::Ordering::Equal);
+
+        // Editing a task keeps its identity, a copy of the task gets its own
+        let mut t3 = Task::from_todotxt(String::from("call mom")).unwrap();
+        let id = t3.id;
+        t3.set_content(String::from("call dad"));
+        t3.set_completed();
+        assert_eq!(t3.id, id);
+        assert_ne!(t3.clone().id, id);
+    }
+
+    #[test]
+    fn recap() {
+        let t1 = Task::from_todotxt(String::from("(B) 2021-10-01 call mom @phone due:2021-10-20")).unwrap();
+        let en = |key| Lang::En.tr(key);
+        let fr = |key| Lang::Fr.tr(key);
+        assert_eq!(t1.recap_str(en, "%Y-%m-%d"), "𝐓𝐚𝐬𝐤 : call mom @phone\n𝐒𝐭𝐚𝐭𝐮𝐬 : To do\n𝐏𝐫𝐢𝐨𝐫𝐢𝐭𝐲 : B\n𝐂𝐫𝐞𝐚𝐭𝐞𝐝 𝐨𝐧 : 2021-10-01\n𝐃𝐮𝐞 𝐝𝐚𝐭𝐞 : 2021-10-20\n𝐂𝐨𝐧𝐭𝐞𝐱𝐭 𝐭𝐚𝐠𝐬 : phone");
+        assert_eq!(t1.row_str("%d/%m/%Y"), "(B) 20/10/2021 : call mom @phone");
+        assert!(t1.recap_str(fr, "%d/%m/%Y").contains("\n𝐄\u{301}𝐜𝐡𝐞\u{301}𝐚𝐧𝐜𝐞 : 20/10/2021\n"));
+        assert!(t1.recap_str(fr, "%Y-%m-%d").starts_with("𝐓𝐚\u{302}𝐜𝐡𝐞 : call mom @phone\n𝐒𝐭𝐚𝐭𝐮𝐭 : À faire"));
+    }
+
+    #[test]
+    fn bold_text() {
+        assert_eq!(bold("Due 2 @home"), "𝐃𝐮𝐞 𝟐 @𝐡𝐨𝐦𝐞");
+        assert_eq!(bold("Créée"), "𝐂𝐫𝐞\u{301}𝐞\u{301}𝐞");
+        // A letter without a bold form keeps the whole text as is
+        assert_eq!(bold("Œuvre"), "Œuvre");
+    }
 }
@@ -0,0 +1,41 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty directory for a test, removed when dropped, even if the test fails
+pub struct TestDir {
+    path : PathBuf
+}
+
+impl TestDir {
+    /// Create an empty directory for a test
+    /// 
+    /// Arguments:
+    /// * `name` - the name of the test, making the directory unique
+    pub fn new(name : &str) -> TestDir {
+        let path = std::env::temp_dir().join(format!("rofitodo_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestDir { path }
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
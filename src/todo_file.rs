@@ -0,0 +1,740 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::task::Task;
+use crate::merge::{merge3, Conflict, Origin, Resolution};
+
+/// A line of a todo.txt file
+enum Line {
+    /// A task, referenced by its identifier
+    Task(usize),
+    /// A line which is not a task (blank or malformed line), kept as is
+    Raw(String)
+}
+
+/// The changes made to the tasks when merging the modifications made to the file outside RofiTodo
+#[derive(Default)]
+pub struct Changes {
+    /// The identifiers of the removed tasks
+    pub removed : Vec<usize>,
+    /// The added tasks
+    pub added : Vec<Task>
+}
+
+/// The layout of a todo.txt file
+/// 
+/// Remember the order of the tasks and the lines which are not tasks,
+/// so that the file can be written back without altering the untouched lines
+pub struct TodoFile {
+    /// The path of the file
+    path : PathBuf,
+    /// The lines of the file, in order
+    lines : Vec<Line>,
+    /// The line ending used in the file (`\n` or `\r\n`)
+    line_ending : &'static str,
+    /// Does the last line end with a line ending
+    trailing_newline : bool,
+    /// The number of backups of the previous versions of the file to keep
+    backups : usize,
+    /// Were the backups rotated in this session, the versions saved during the session not being backed up
+    backed_up : bool,
+    /// The content of the file when it was last read or written, used to detect external modifications
+    snapshot : String
+}
+
+impl TodoFile {
+    /// Create the layout of an empty file
+    /// 
+    /// Arguments:
+    /// 
+    /// * `path` - the path of the file
+    pub fn new(path: PathBuf) -> Self {
+        TodoFile { path, lines : vec![], line_ending : "\n", trailing_newline : true, backups : 0, backed_up : false, snapshot : String::new() }
+    }
+
+    /// Set the number of backups to keep when saving the file
+    /// 
+    /// The backups are rotated once per session, on the first save, so they keep the versions of the previous sessions
+    /// 
+    /// Arguments:
+    /// 
+    /// * `backups` - the number of backups, `0` to disable them
+    pub fn set_backups(&mut self, backups: usize) {
+        self.backups = backups;
+    }
+
+    /// Parse the content of a todo.txt file
+    /// 
+    /// Return the layout of the file and the tasks it contains
+    /// 
+    /// Arguments:
+    /// 
+    /// * `path` - the path of the file
+    /// * `content` - the content of the file
+    pub fn parse(path: PathBuf, content: &str) -> (Self, Vec<Task>) {
+        let mut file = Self::new(path);
+        let mut tasks = Vec::<Task>::new();
+        let (lines, line_ending, trailing_newline) = split_lines(content);
+        file.line_ending = line_ending;
+        file.trailing_newline = trailing_newline;
+        file.snapshot = String::from(content);
+        for line in lines {
+            match Task::from_todotxt(String::from(line)) {
+                Ok(task) => {
+                    file.lines.push(Line::Task(task.get_id()));
+                    tasks.push(task);
+                },
+                Err(_) => file.lines.push(Line::Raw(String::from(line)))
+            }
+        }
+        (file, tasks)
+    }
+
+    /// Load a todo.txt file, creating it if it doesn't exist
+    /// 
+    /// Return the layout of the file and the tasks it contains
+    /// 
+    /// Arguments:
+    /// 
+    /// * `path` - the path of the file
+    pub fn load(path: PathBuf) -> Result<(Self, Vec<Task>), String> {
+        if !path.exists() {
+            // Creating the directories of the file, such as ~/.local/share/rofitodo
+            let created = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+                _ => Ok(())
+            };
+            if let Err(e) = created.and_then(|_| write_atomic(&path, 0, |_| Ok(()))) {
+                return Err(format!("{}: {}", path.display(), e));
+            }
+            return Ok((Self::new(path), vec![]));
+        }
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(Self::parse(path, &content)),
+            Err(e) => Err(format!("{}: {}", path.display(), e))
+        }
+    }
+
+    /// Get the line number of a task in the file, starting at 1
+    /// 
+    /// Arguments:
+    /// 
+    /// * `id` - the identifier of the task
+    pub fn line_number(&self, id: usize) -> Option<usize> {
+        self.lines.iter().position(|line| matches!(line, Line::Task(task) if *task == id)).map(|n| n + 1)
+    }
+
+    /// Get the identifier of the task at a line of the file
+    /// 
+    /// Arguments:
+    /// 
+    /// * `line_number` - the line number, starting at 1
+    pub fn task_at(&self, line_number: usize) -> Option<usize> {
+        match self.lines.get(line_number.checked_sub(1)?) {
+            Some(Line::Task(id)) => Some(*id),
+            _ => None
+        }
+    }
+
+    /// Render the file with the current tasks
+    /// 
+    /// Tasks keep their position, removed tasks are dropped and new tasks are appended at the end of the file.
+    /// The layout is updated to match the rendered content
+    /// 
+    /// Arguments:
+    /// 
+    /// * `tasks` - all the tasks of the file
+    pub fn render(&mut self, tasks: &BTreeSet<Rc<Task>>) -> String {
+        let mut by_id = tasks.iter().map(|t| (t.get_id(), t)).collect::<HashMap<_, _>>();
+        self.lines.retain(|line| match line {
+            Line::Task(id) => by_id.contains_key(id),
+            Line::Raw(_) => true
+        });
+        let mut rendered = Vec::<String>::new();
+        for line in &self.lines {
+            match line {
+                Line::Task(id) => rendered.push(by_id.remove(id).unwrap().to_todotxt()),
+                Line::Raw(raw) => rendered.push(raw.clone())
+            }
+        }
+        let mut new_tasks = by_id.into_values().collect::<Vec<_>>();
+        new_tasks.sort_by_key(|t| t.get_id());
+        for task in new_tasks {
+            self.lines.push(Line::Task(task.get_id()));
+            rendered.push(task.to_todotxt());
+        }
+        let mut content = rendered.join(self.line_ending);
+        if self.trailing_newline && !self.lines.is_empty() {
+            content.push_str(self.line_ending);
+        }
+        content
+    }
+
+    /// Write the tasks to the file
+    /// 
+    /// The file is replaced atomically, so it is never left half written.
+    /// If the file was modified outside RofiTodo since it was loaded, both versions are merged line by line
+    /// and the returned `Changes` must be applied to the tasks
+    /// 
+    /// Arguments:
+    /// 
+    /// * `tasks` - all the tasks of the file
+    /// * `resolve` - a closure choosing how to resolve a conflict between both versions
+    pub fn save(&mut self, tasks: &BTreeSet<Rc<Task>>, resolve: impl FnMut(&Conflict) -> Resolution) -> Result<Changes, String> {
+        let mut content = self.render(tasks);
+        let mut changes = Changes::default();
+        let on_disk = match fs::read_to_string(&self.path) {
+            Ok(theirs) => {
+                if theirs != self.snapshot {
+                    content = self.merge(&content, &theirs, resolve, &mut changes);
+                }
+                Some(theirs)
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("{}: {}", self.path.display(), e))
+        };
+        // Nothing to write, and no backup to rotate, when the file is already up to date
+        if on_disk.as_ref() == Some(&content) {
+            self.snapshot = content;
+            return Ok(changes);
+        }
+        let backups = if self.backed_up { 0 } else { self.backups };
+        match write_atomic(&self.path, backups, |file| file.write_all(content.as_bytes())) {
+            Ok(_) => {
+                self.backed_up = true;
+                self.snapshot = content;
+                Ok(changes)
+            },
+            Err(e) => Err(format!("{}: {}", self.path.display(), e))
+        }
+    }
+
+    /// Merge the rendered file with the version modified outside RofiTodo
+    /// 
+    /// The layout is updated to match the merged content, which is returned
+    /// 
+    /// Arguments:
+    /// 
+    /// * `ours` - the rendered file
+    /// * `theirs` - the content of the file modified outside RofiTodo
+    /// * `resolve` - a closure choosing how to resolve a conflict between both versions
+    /// * `changes` - where to store the changes made to the tasks
+    fn merge(&mut self, ours: &str, theirs: &str, resolve: impl FnMut(&Conflict) -> Resolution, changes: &mut Changes) -> String {
+        let (base_lines, _, _) = split_lines(&self.snapshot);
+        let (ours_lines, _, _) = split_lines(ours);
+        let (theirs_lines, _, _) = split_lines(theirs);
+        let mut old_layout = self.lines.drain(..).map(Some).collect::<Vec<_>>();
+        let mut rendered = Vec::<&str>::new();
+        for origin in merge3(&base_lines, &ours_lines, &theirs_lines, resolve) {
+            match origin {
+                Origin::Ours(n) => if let Some(line) = old_layout[n].take() {
+                    self.lines.push(line);
+                    rendered.push(ours_lines[n]);
+                },
+                Origin::Theirs(n) => {
+                    match Task::from_todotxt(String::from(theirs_lines[n])) {
+                        Ok(task) => {
+                            self.lines.push(Line::Task(task.get_id()));
+                            changes.added.push(task);
+                        },
+                        Err(_) => self.lines.push(Line::Raw(String::from(theirs_lines[n])))
+                    }
+                    rendered.push(theirs_lines[n]);
+                }
+            }
+        }
+        for line in old_layout.into_iter().flatten() {
+            if let Line::Task(id) = line {
+                changes.removed.push(id);
+            }
+        }
+        let mut content = rendered.join(self.line_ending);
+        if self.trailing_newline && !self.lines.is_empty() {
+            content.push_str(self.line_ending);
+        }
+        content
+    }
+
+    /// Replace a todo.txt file by one of its backups
+    /// 
+    /// The current content of the file becomes the first backup
+    /// 
+    /// Arguments:
+    /// 
+    /// * `path` - the path of the file
+    /// * `n` - the number of the backup, `1` being the most recent
+    /// * `backups` - the number of backups to keep
+    pub fn restore_backup(path: &Path, n: usize, backups: usize) -> Result<(), String> {
+        let backup = backup_path(&resolve_symlinks(path), n);
+        let content = match fs::read(&backup) {
+            Ok(content) => content,
+            Err(e) => return Err(format!("{}: {}", backup.display(), e))
+        };
+        match write_atomic(path, backups, |file| file.write_all(&content)) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", path.display(), e))
+        }
+    }
+}
+
+/// Find the task list file used when no file is given
+/// 
+/// The file is `$TODO_FILE` or `$TODO_DIR/todo.txt`, like todo.sh, then `$XDG_DATA_HOME/rofitodo/todo.txt`,
+/// `$XDG_DATA_HOME` defaulting to `~/.local/share`.
+/// Returns the path of the file and where it comes from
+pub fn default_path() -> (PathBuf, &'static str) {
+    find_default_path(|name| std::env::var_os(name), home::home_dir())
+}
+
+/// Find the task list file used when no file is given, from the environment variables
+/// 
+/// Arguments:
+/// 
+/// * `var` - get the value of an environment variable
+/// * `home` - the home directory of the user
+fn find_default_path(var: impl Fn(&str) -> Option<OsString>, home: Option<PathBuf>) -> (PathBuf, &'static str) {
+    // Empty variables are ignored, like unset ones
+    let path = |name| var(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if let Some(file) = path("TODO_FILE") {
+        return (file, "$TODO_FILE");
+    }
+    if let Some(dir) = path("TODO_DIR") {
+        return (dir.join("todo.txt"), "$TODO_DIR");
+    }
+    let file = Path::new("rofitodo").join("todo.txt");
+    match (path("XDG_DATA_HOME").filter(|dir| dir.is_absolute()), home) {
+        (Some(dir), _) => (dir.join(file), "$XDG_DATA_HOME"),
+        (None, Some(home)) => (home.join(".local").join("share").join(file), "~/.local/share"),
+        (None, None) => (PathBuf::from("./todo.txt"), "the current directory")
+    }
+}
+
+/// Get the path of the archive of another task list than the main one, named after its file (`work.done.txt` for `work.txt`)
+/// 
+/// The archives of the lists never collide, even with the `done.txt` archive of the main list
+/// 
+/// Arguments:
+/// 
+/// * `path` - the path of the task list file
+pub fn list_done_path(path: &Path) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".done.txt");
+    path.with_file_name(name)
+}
+
+/// Append tasks at the end of a todo.txt file, such as the `done.txt` archive
+/// 
+/// The file is created if it does not exist, its content and line endings are kept
+/// 
+/// Arguments:
+/// 
+/// * `path` - the path of the file
+/// * `tasks` - the tasks to append
+pub fn append_tasks<'a>(path: &Path, tasks: impl IntoIterator<Item = &'a Task>) -> Result<(), String> {
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e))
+    };
+    let (_, line_ending, trailing_newline) = split_lines(&existing);
+    let mut content = String::new();
+    if !trailing_newline {
+        content.push_str(line_ending);
+    }
+    for task in tasks {
+        content.push_str(&task.to_todotxt());
+        content.push_str(line_ending);
+    }
+    let appended = fs::OpenOptions::new().create(true).append(true).open(path).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    match appended {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Split the content of a file into lines
+/// 
+/// Returns the lines, the line ending used in the file and whether the last line ends with a line ending
+/// 
+/// Arguments:
+/// 
+/// * `content` - the content of the file
+fn split_lines(content: &str) -> (Vec<&str>, &'static str, bool) {
+    if content.is_empty() {
+        return (vec![], "\n", true);
+    }
+    let line_ending = if content.split('\n').next().unwrap().ends_with('\r') { "\r\n" } else { "\n" };
+    let mut lines = content.split(line_ending).collect::<Vec<_>>();
+    // Splitting a file ending with a line ending gives a last empty line
+    let trailing_newline = lines.last() == Some(&"");
+    if trailing_newline {
+        lines.pop();
+    }
+    (lines, line_ending, trailing_newline)
+}
+
+/// Get the path of the n-th backup of a file (`todo.txt.bak.1`...)
+/// 
+/// Arguments:
+/// 
+/// * `path` - the path of the file
+/// * `n` - the number of the backup
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".bak.{}", n));
+    path.with_file_name(name)
+}
+
+/// Get the path of the file a path links to, or the path itself if it doesn't exist yet
+/// 
+/// Arguments:
+/// 
+/// * `path` - the path of the file
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Replace a file atomically
+/// 
+/// The content is written and synced to a temporary file in the same directory, which is then renamed over the file.
+/// If anything fails before the rename, the file is left untouched.
+/// The previous versions of the file are kept in rotating backups, rotated once the file is replaced.
+/// A symbolic link is followed, so the file it links to is replaced, keeping its permissions
+/// 
+/// Arguments:
+/// 
+/// * `path` - the path of the file
+/// * `backups` - the number of backups to keep
+/// * `write` - a closure writing the new content to the temporary file
+pub fn write_atomic(path: &Path, backups: usize, write: impl FnOnce(&mut fs::File) -> io::Result<()>) -> io::Result<()> {
+    let path = &resolve_symlinks(path);
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(tmp_name);
+
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        write(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    // The previous version is copied aside, and only becomes a backup once the file is replaced,
+    // so a failed rename leaves the existing backups untouched
+    let pending = tmp.with_extension("bak");
+    let backed_up = backups > 0 && path.exists();
+    if backed_up {
+        if let Err(e) = fs::copy(path, &pending) {
+            let _ = fs::remove_file(&tmp);
+            let _ = fs::remove_file(&pending);
+            return Err(e);
+        }
+    }
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        let _ = fs::remove_file(&pending);
+        return Err(e);
+    }
+    if backed_up {
+        for n in (1..backups).rev() {
+            let backup = backup_path(path, n);
+            if backup.exists() {
+                fs::rename(&backup, backup_path(path, n + 1))?;
+            }
+        }
+        fs::rename(&pending, backup_path(path, 1))?;
+    }
+    // Syncing the directory to make the rename durable
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = fs::File::open(if dir.as_os_str().is_empty() { Path::new(".") } else { dir }) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod todo_file_tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use proptest::prelude::*;
+
+    /// Parse a content and render it back without any change
+    fn round_trip(content: &str) -> String {
+        let (mut file, tasks) = TodoFile::parse(PathBuf::from("todo.txt"), content);
+        let tasks = tasks.into_iter().map(Rc::new).collect::<BTreeSet<_>>();
+        file.render(&tasks)
+    }
+
+    #[test]
+    fn keep_layout() {
+        let content = "(A) call mom @phone\n\n2021-13-45 malformed date\nx 2021-10-20 2021-10-01 pay rent due:2021-10-20 rec:1m\n   \nb task\na task";
+        assert_eq!(round_trip(content), content);
+        let content = "b task\r\na task t:2021-10-01 due:2021-10-02\r\n\r\n";
+        assert_eq!(round_trip(content), content);
+        assert_eq!(round_trip(""), "");
+        assert_eq!(round_trip("\n"), "\n");
+    }
+
+    #[test]
+    fn edit_tasks() {
+        let (mut file, tasks) = TodoFile::parse(PathBuf::from("todo.txt"), "b task\n# a comment\na task due:2021-10-01 rec:1w\nc task\n");
+        let mut tasks = tasks.into_iter().map(Rc::new).collect::<BTreeSet<_>>();
+
+        // Editing the second task keeps its position and the order of its tags
+        let a = tasks.iter().find(|t| t.get_content() == "a task").unwrap().clone();
+        tasks.remove(&a);
+        let mut a = Rc::try_unwrap(a).unwrap();
+        a.set_due(chrono::NaiveDate::from_ymd_opt(2021, 10, 8));
+        a.priority = Some('A');
+        tasks.insert(Rc::new(a));
+
+        // Removing the last task and adding a new one
+        let c = tasks.iter().find(|t| t.get_content() == "c task").unwrap().clone();
+        tasks.remove(&c);
+        tasks.insert(Rc::new(Task::from_todotxt(String::from("d task")).unwrap()));
+
+        assert_eq!(file.render(&tasks), "b task\n# a comment\n(A) a task due:2021-10-08 rec:1w\nd task\n");
+        // The layout follows the rendered content
+        assert_eq!(file.render(&tasks), "b task\n# a comment\n(A) a task due:2021-10-08 rec:1w\nd task\n");
+
+        let d = tasks.iter().find(|t| t.get_content() == "d task").unwrap().get_id();
+        assert_eq!(file.line_number(d), Some(4));
+        assert_eq!(file.task_at(4), Some(d));
+        assert_eq!(file.task_at(0), None);
+        assert_eq!(file.task_at(5), None);
+    }
+
+    #[test]
+    fn failed_write_keeps_file() {
+        let dir = TestDir::new("failed_write");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "a task\n").unwrap();
+
+        let result = write_atomic(&path, 2, |file| {
+            file.write_all(b"half a ta")?;
+            Err(io::Error::other("disk full"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a task\n");
+        // No backup nor temporary file is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn failed_rename_keeps_file() {
+        let dir = TestDir::new("failed_rename");
+        // A directory can't be replaced by a file
+        let path = dir.join("todo.txt");
+        fs::create_dir(&path).unwrap();
+
+        let (mut file, _) = TodoFile::parse(path.clone(), "");
+        assert!(file.save(&BTreeSet::new(), |_| Resolution::Both).is_err());
+        assert!(path.is_dir());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn default_file() {
+        let home = Some(PathBuf::from("/home/user"));
+        let env = |vars: &'static [(&'static str, &'static str)]| move |name: &str| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| OsString::from(v));
+        assert_eq!(find_default_path(env(&[("TODO_FILE", "/tmp/t.txt"), ("TODO_DIR", "/tmp/todo")]), home.clone()), (PathBuf::from("/tmp/t.txt"), "$TODO_FILE"));
+        assert_eq!(find_default_path(env(&[("TODO_FILE", ""), ("TODO_DIR", "/tmp/todo")]), home.clone()), (PathBuf::from("/tmp/todo/todo.txt"), "$TODO_DIR"));
+        assert_eq!(find_default_path(env(&[("XDG_DATA_HOME", "/data")]), home.clone()), (PathBuf::from("/data/rofitodo/todo.txt"), "$XDG_DATA_HOME"));
+        // A relative $XDG_DATA_HOME is invalid and ignored
+        assert_eq!(find_default_path(env(&[("XDG_DATA_HOME", "data")]), home.clone()), (PathBuf::from("/home/user/.local/share/rofitodo/todo.txt"), "~/.local/share"));
+        assert_eq!(find_default_path(env(&[]), None), (PathBuf::from("./todo.txt"), "the current directory"));
+    }
+
+    #[test]
+    fn list_archive() {
+        assert_eq!(list_done_path(Path::new("/home/user/work.txt")), PathBuf::from("/home/user/work.done.txt"));
+        assert_eq!(list_done_path(Path::new("/home/user/work/todo.txt")), PathBuf::from("/home/user/work/todo.done.txt"));
+    }
+
+    #[test]
+    fn create_missing_file() {
+        let dir = TestDir::new("missing");
+        let path = dir.join("rofitodo").join("todo.txt");
+        let (_, tasks) = TodoFile::load(path.clone()).unwrap();
+        assert!(tasks.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn rotate_and_restore_backups() {
+        let dir = TestDir::new("backups");
+        let path = dir.join("todo.txt");
+        for content in ["v1\n", "v2\n", "v3\n", "v4\n"] {
+            write_atomic(&path, 2, |file| file.write_all(content.as_bytes())).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v4\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "v3\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "v2\n");
+        assert!(!backup_path(&path, 3).exists());
+
+        TodoFile::restore_backup(&path, 2, 2).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v2\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "v4\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "v3\n");
+
+        assert!(TodoFile::restore_backup(&path, 5, 2).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keep_symlink_and_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        let dir = TestDir::new("symlink");
+        let target = dir.join("real.txt");
+        let link = dir.join("todo.txt");
+        fs::write(&target, "v1\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        write_atomic(&link, 1, |file| file.write_all(b"v2\n")).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "v2\n");
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o600);
+        // The backups are kept next to the file the link points to
+        assert_eq!(fs::read_to_string(backup_path(&target, 1)).unwrap(), "v1\n");
+
+        TodoFile::restore_backup(&link, 1, 1).unwrap();
+        assert_eq!(fs::read_to_string(&link).unwrap(), "v1\n");
+    }
+
+    #[test]
+    fn unchanged_save_keeps_backups() {
+        let dir = TestDir::new("unchanged");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "a task\n").unwrap();
+        let (mut file, tasks) = TodoFile::load(path.clone()).unwrap();
+        let tasks = tasks.into_iter().map(Rc::new).collect::<BTreeSet<_>>();
+        file.save(&tasks, |_| panic!("unexpected conflict")).unwrap();
+        assert!(!backup_path(&path, 1).exists());
+    }
+
+    #[test]
+    fn rotate_backups_once_per_session() {
+        let dir = TestDir::new("session_backups");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "a task\n").unwrap();
+        for tasks in [&["b task", "c task"][..], &["c task"]] {
+            let (mut file, _) = TodoFile::load(path.clone()).unwrap();
+            file.set_backups(2);
+            for n in 1..=tasks.len() {
+                let tasks = tasks[..n].iter().map(|t| Rc::new(Task::from_todotxt(String::from(*t)).unwrap())).collect::<BTreeSet<_>>();
+                file.save(&tasks, |_| panic!("unexpected conflict")).unwrap();
+            }
+        }
+        // Only the versions at the start of the sessions are kept
+        assert_eq!(fs::read_to_string(&path).unwrap(), "c task\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "b task\nc task\n");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "a task\n");
+    }
+
+    #[test]
+    fn append_to_archive() {
+        let dir = TestDir::new("archive_append");
+        let path = dir.join("done.txt");
+        let done = ["x 2021-10-02 a task", "x b task +project"].iter().map(|t| Task::from_todotxt(String::from(*t)).unwrap()).collect::<Vec<_>>();
+        append_tasks(&path, &done).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x 2021-10-02 a task\nx b task +project\n");
+        append_tasks(&path, &done[..1]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x 2021-10-02 a task\nx b task +project\nx 2021-10-02 a task\n");
+
+        // Keeping the line endings of the archive, even without a final line ending
+        fs::write(&path, "x c task\r\nx d task").unwrap();
+        append_tasks(&path, &done[1..]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x c task\r\nx d task\r\nx b task +project\r\n");
+    }
+
+    #[test]
+    fn merge_external_modifications() {
+        let dir = TestDir::new("merge");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "a task\nb task\nc task\n").unwrap();
+        let (mut file, tasks) = TodoFile::load(path.clone()).unwrap();
+        let mut tasks = tasks.into_iter().map(Rc::new).collect::<BTreeSet<_>>();
+
+        // We complete the first task and add a new one
+        let a = tasks.iter().find(|t| t.get_content() == "a task").unwrap().clone();
+        tasks.remove(&a);
+        let mut a = Rc::try_unwrap(a).unwrap();
+        a.completion = true;
+        tasks.insert(Rc::new(a));
+        tasks.insert(Rc::new(Task::from_todotxt(String::from("d task")).unwrap()));
+        // Meanwhile, the third task is edited and another one is added in another editor
+        fs::write(&path, "a task\nb task\n(A) c task\ne task\n").unwrap();
+
+        let c = tasks.iter().find(|t| t.get_content() == "c task").unwrap().get_id();
+        let changes = file.save(&tasks, |_| panic!("unexpected conflict")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x a task\nb task\n(A) c task\ne task\nd task\n");
+        assert_eq!(changes.removed, vec![c]);
+        assert_eq!(changes.added.iter().map(|t| t.to_todotxt()).collect::<Vec<_>>(), vec!["(A) c task", "e task"]);
+
+        // Once the changes are applied, saving again doesn't change the file
+        tasks.retain(|t| !changes.removed.contains(&t.get_id()));
+        tasks.extend(changes.added.into_iter().map(Rc::new));
+        let changes = file.save(&tasks, |_| panic!("unexpected conflict")).unwrap();
+        assert!(changes.removed.is_empty() && changes.added.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x a task\nb task\n(A) c task\ne task\nd task\n");
+    }
+
+    #[test]
+    fn merge_conflicting_modifications() {
+        let dir = TestDir::new("merge_conflict");
+        let path = dir.join("todo.txt");
+        fs::write(&path, "a task\nb task\n").unwrap();
+        let (mut file, tasks) = TodoFile::load(path.clone()).unwrap();
+        let mut tasks = tasks.into_iter().map(Rc::new).collect::<BTreeSet<_>>();
+
+        let b = tasks.iter().find(|t| t.get_content() == "b task").unwrap().clone();
+        tasks.remove(&b);
+        let mut b = Rc::try_unwrap(b).unwrap();
+        b.priority = Some('B');
+        tasks.insert(Rc::new(b));
+        fs::write(&path, "a task\n(C) b task\n").unwrap();
+
+        let changes = file.save(&tasks, |conflict| {
+            assert_eq!(conflict.ours, vec!["(B) b task"]);
+            assert_eq!(conflict.theirs, vec!["(C) b task"]);
+            Resolution::Ours
+        }).unwrap();
+        assert!(changes.removed.is_empty() && changes.added.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a task\n(B) b task\n");
+    }
+
+    proptest! {
+        #[test]
+        fn task_round_trip(line in r"(x )?(\([A-Z]\) )?([0-9]{4}-[0-9]{2}-[0-9]{2} ){0,2}[a-z@+: ]{0,12}( [a-z]{1,3}:[a-z0-9-]{1,10}){0,3} ?") {
+            if let Ok(task) = Task::from_todotxt(line.clone()) {
+                prop_assert_eq!(task.to_todotxt(), line);
+            }
+        }
+
+        #[test]
+        fn any_line_round_trip(line in r"[^\r\n]*") {
+            if let Ok(task) = Task::from_todotxt(line.clone()) {
+                prop_assert_eq!(task.to_todotxt(), line);
+            }
+        }
+
+        #[test]
+        fn file_round_trip(content in r"((x )?(\([A-C]\) )?(2021-0[1-9]-[0-3][0-9] )?[a-z@+: ]{0,8}( due:2021-10-0[1-9])?(\r)?\n){0,6}[a-z ]{0,4}") {
+            prop_assert_eq!(round_trip(&content), content);
+        }
+    }
+}
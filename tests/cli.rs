@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+#[path = "../src/test_dir.rs"]
+mod test_dir;
+use test_dir::TestDir;
+
+/// Run rofitodo on a task list file
+fn rofitodo(file: &Path, args: &[&str]) -> Output {
+    // The configuration file is looked for next to the task list file, not in the user's one
+    Command::new(env!("CARGO_BIN_EXE_rofitodo"))
+        .env("XDG_CONFIG_HOME", file.parent().unwrap())
+        .arg("--config")
+        .arg(file)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Run rofitodo on a task list file and return its output, checking it succeeded
+fn stdout(file: &Path, args: &[&str]) -> String {
+    let output = rofitodo(file, args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn add_and_list() {
+    let dir = TestDir::new("cli_add");
+    let file = dir.join("todo.txt");
+    fs::write(&file, "# work\n2021-10-01 write report +Work due:2021-10-20\n").unwrap();
+
+    assert_eq!(stdout(&file, &["add", "2021-10-02 call bob @phone due:2021-10-05"]), "3 2021-10-02 call bob @phone due:2021-10-05\n");
+    assert_eq!(stdout(&file, &["add", "(A) 2021-10-03 fix bug +Work"]), "4 (A) 2021-10-03 fix bug +Work\n");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "# work\n2021-10-01 write report +Work due:2021-10-20\n2021-10-02 call bob @phone due:2021-10-05\n(A) 2021-10-03 fix bug +Work\n");
+
+    // Tasks added without a creation date get today's date
+    let added = stdout(&file, &["add", "buy milk"]);
+    assert!(added.starts_with("5 ") && added.ends_with(" buy milk\n"));
+
+    assert_eq!(stdout(&file, &["list", "--sort", "due", "--project", "Work"]), "2 2021-10-01 write report +Work due:2021-10-20\n4 (A) 2021-10-03 fix bug +Work\n");
+    assert_eq!(stdout(&file, &["list", "--context", "@phone"]), "3 2021-10-02 call bob @phone due:2021-10-05\n");
+    assert_eq!(stdout(&file, &["--sort", "priority", "list"]).lines().next(), Some("4 (A) 2021-10-03 fix bug +Work"));
+
+    assert!(!rofitodo(&file, &["add", ""]).status.success());
+
+    // A task list file which can't be read is an error
+    fs::create_dir(dir.join("directory.txt")).unwrap();
+    let output = rofitodo(&dir.join("directory.txt"), &["add", "buy milk"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn edit_tasks() {
+    let dir = TestDir::new("cli_edit");
+    let file = dir.join("todo.txt");
+    fs::write(&file, "a task\n\nb task due:2021-10-05\nc task\n").unwrap();
+
+    assert_eq!(stdout(&file, &["pri", "1", "b"]), "1 (B) a task\n");
+    assert_eq!(stdout(&file, &["due", "4", "2021-11-01"]), "4 c task due:2021-11-01\n");
+    assert_eq!(stdout(&file, &["due", "3"]), "3 b task\n");
+    assert_eq!(stdout(&file, &["pri", "1"]), "1 a task\n");
+    assert_eq!(stdout(&file, &["rm", "3"]), "3 b task\n");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "a task\n\nc task due:2021-11-01\n");
+
+    // The line numbers of the file are used, the removed task left no empty line
+    assert_eq!(stdout(&file, &["list"]), "1 a task\n3 c task due:2021-11-01\n");
+
+    for args in [&["rm", "2"][..], &["rm", "4"], &["pri", "1", "1"], &["due", "1", "tomorrow"]] {
+        let output = rofitodo(&file, args);
+        assert!(!output.status.success());
+        assert!(!output.stderr.is_empty());
+    }
+    assert_eq!(fs::read_to_string(&file).unwrap(), "a task\n\nc task due:2021-11-01\n");
+}
+
+#[test]
+fn complete_tasks() {
+    let dir = TestDir::new("cli_done");
+    let file = dir.join("todo.txt");
+    fs::write(&file, "water plants due:2021-10-01 rec:+1w\npay rent\n").unwrap();
+
+    let done = stdout(&file, &["done", "2"]);
+    assert!(done.starts_with("2 x ") && done.ends_with(" pay rent\n"));
+    assert!(!rofitodo(&file, &["done", "2"]).status.success());
+
+    // Completing a recurring task adds its next occurrence
+    stdout(&file, &["done", "1"]);
+    let next = stdout(&file, &["list"]);
+    assert!(next.starts_with("3 ") && next.ends_with(" water plants due:2021-10-08 rec:+1w\n"));
+    assert_eq!(stdout(&file, &["list", "--all"]).lines().count(), 3);
+}
+
+#[test]
+fn configuration_file() {
+    let dir = TestDir::new("cli_settings");
+    let file = dir.join("todo.txt");
+    fs::write(&file, "a task\n(A) b task\n").unwrap();
+    fs::create_dir_all(dir.join("rofitodo")).unwrap();
+
+    fs::write(dir.join("rofitodo").join("config.toml"), "sort = \"priority\"\n").unwrap();
+    assert_eq!(stdout(&file, &["list"]), "2 (A) b task\n1 a task\n");
+    // The options take precedence over the configuration file
+    assert_eq!(stdout(&file, &["--sort", "content", "list"]), "1 a task\n2 (A) b task\n");
+
+    fs::write(dir.join("rofitodo").join("config.toml"), "sort = \"priority\"\nsorting = \"due\"\n").unwrap();
+    let output = rofitodo(&file, &["list"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown field `sorting`"));
+
+    // A flag enabled in the configuration file can be disabled on the command line, the last option winning
+    fs::write(dir.join("rofitodo").join("config.toml"), "journal = true\n").unwrap();
+    stdout(&file, &["--no-journal", "add", "c task"]);
+    assert!(!dir.join("todo.txt.journal").exists());
+    stdout(&file, &["--journal", "--no-journal", "--journal", "add", "d task"]);
+    assert!(dir.join("todo.txt.journal").exists());
+
+    // The invalid options are errors, reported before any menu is shown
+    fs::write(dir.join("rofitodo").join("config.toml"), "").unwrap();
+    for args in [&["--key", "archive=Alt+a", "list"][..], &["--list", "work"]] {
+        let output = rofitodo(&file, args);
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        assert!(!output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn restore_backups() {
+    let dir = TestDir::new("cli_backups");
+    let file = dir.join("todo.txt");
+    fs::write(&file, "a task\n").unwrap();
+
+    stdout(&file, &["add", "b task"]);
+    stdout(&file, &["--restore-backup", "1"]);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "a task\n");
+
+    let output = rofitodo(&file, &["--restore-backup", "3"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("todo.txt.bak.3"));
+}